@@ -0,0 +1,110 @@
+// Azure Pipelines logging commands, which annotate the build with each diagnostic
+// and set the result of the task
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::reviewdog as rd;
+
+// The least severe diagnostics that make the task fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailOn {
+    Error,
+    Warning,
+    Info,
+    Never,
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(FailOn::Error),
+            "warning" => Ok(FailOn::Warning),
+            "info" => Ok(FailOn::Info),
+            "never" => Ok(FailOn::Never),
+            _ => Err(format!("Unknown severity to fail on '{}'", s)),
+        }
+    }
+}
+
+// Writes a task.logissue command for each diagnostic, then a task.complete command
+// with the result: Failed if there are diagnostics as severe as fail_on,
+// SucceededWithIssues if there are others, or Succeeded.
+pub fn write(
+    out: &mut dyn Write,
+    all_diags: &[rd::Diagnostic],
+    overall_sev: rd::Severity,
+    fail_on: FailOn,
+) -> io::Result<()> {
+    for diag in all_diags {
+        write_issue(out, diag)?;
+    }
+
+    let count = |severity| {
+        all_diags
+            .iter()
+            .filter(|d| d.severity == Some(severity))
+            .count()
+    };
+    let (errors, warnings) = (count(rd::Severity::Error), count(rd::Severity::Warning));
+    // The overall severity also counts errors that couldn't be reported on a file
+    let has_error = errors > 0 || overall_sev == rd::Severity::Error;
+    let has_warning = warnings > 0 || overall_sev == rd::Severity::Warning;
+    let failed = match fail_on {
+        FailOn::Error => has_error,
+        FailOn::Warning => has_error || has_warning,
+        FailOn::Info => has_error || has_warning || !all_diags.is_empty(),
+        FailOn::Never => false,
+    };
+    let result = if failed {
+        "Failed"
+    } else if has_error || has_warning || !all_diags.is_empty() {
+        "SucceededWithIssues"
+    } else {
+        "Succeeded"
+    };
+    writeln!(
+        out,
+        "##vso[task.complete result={};]{} error(s), {} warning(s)",
+        result, errors, warnings
+    )
+}
+
+fn write_issue(out: &mut dyn Write, diag: &rd::Diagnostic) -> io::Result<()> {
+    // Azure Pipelines only has errors and warnings
+    let issue_type = match diag.severity {
+        Some(rd::Severity::Error) => "error",
+        _ => "warning",
+    };
+    write!(
+        out,
+        "##vso[task.logissue type={};sourcepath={};",
+        issue_type,
+        escape_property(&diag.location.path)
+    )?;
+    if let Some(range) = &diag.location.range {
+        if let Some(line) = range.start.line {
+            write!(out, "linenumber={};", line)?;
+        }
+        if let Some(column) = range.start.column {
+            write!(out, "columnnumber={};", column)?;
+        }
+    }
+    if let Some(code) = &diag.code {
+        write!(out, "code={};", escape_property(&code.value))?;
+    }
+    writeln!(out, "]{}", escape_message(&diag.message))
+}
+
+// Escapes the message of a logging command, which must be on one line.
+fn escape_message(text: &str) -> String {
+    text.replace('%', "%AZP25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+// Escapes a property value, which additionally can't contain the ; or ] that end it.
+fn escape_property(text: &str) -> String {
+    escape_message(text).replace(';', "%3B").replace(']', "%5D")
+}
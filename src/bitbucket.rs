@@ -0,0 +1,293 @@
+// Bitbucket Code Insights payloads: a report for the commit, and annotations of the lines it's about,
+// with the field names of either Bitbucket Cloud's or Bitbucket Server's (and Data Center's) API
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::reviewdog as rd;
+
+// Bitbucket allows at most this many characters in an annotation message.
+const MAX_MESSAGE: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    Cloud,
+    Server,
+}
+
+// Both payloads, to be sent with separate requests: the report to
+// .../reports/{id} and the annotations to .../reports/{id}/annotations.
+#[derive(Debug, Serialize)]
+pub struct Insights<R, A> {
+    pub report: R,
+    pub annotations: Vec<A>,
+}
+
+// A report for Bitbucket Cloud's commit reports API.
+#[derive(Debug, Serialize)]
+pub struct CloudReport<'a> {
+    pub title: &'a str,
+    pub details: String,
+    pub reporter: &'static str,
+    pub report_type: &'static str,
+    // PASSED, or FAILED if there are any errors.
+    pub result: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<&'a str>,
+    pub data: Vec<DataField>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CloudAnnotation<'a> {
+    pub external_id: String,
+    // BUG for errors, CODE_SMELL for anything less severe.
+    pub annotation_type: &'static str,
+    pub path: &'a str,
+    // Left out for a problem with the whole file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<&'a str>,
+    // HIGH, MEDIUM or LOW.
+    pub severity: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<&'a str>,
+}
+
+// A report for Bitbucket Server's and Data Center's Code Insights API.
+#[derive(Debug, Serialize)]
+pub struct ServerReport<'a> {
+    pub title: &'a str,
+    pub details: String,
+    pub reporter: &'static str,
+    // PASS, or FAIL if there are any errors.
+    pub result: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<&'a str>,
+    pub data: Vec<DataField>,
+}
+
+// Bitbucket Server annotations have no room for details, only the message.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerAnnotation<'a> {
+    pub external_id: String,
+    pub path: &'a str,
+    // 0 for a problem with the whole file.
+    pub line: u32,
+    pub message: String,
+    // HIGH, MEDIUM or LOW.
+    pub severity: &'static str,
+    // BUG for errors, CODE_SMELL for anything less severe.
+    #[serde(rename = "type")]
+    pub annotation_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataField {
+    pub title: &'static str,
+    #[serde(rename = "type")]
+    pub data_type: &'static str,
+    pub value: u32,
+}
+
+pub fn write(
+    out: &mut dyn Write,
+    all_diags: &[rd::Diagnostic],
+    overall_sev: rd::Severity,
+    source: rd::Source,
+    api: Api,
+) -> Result<(), Error> {
+    match api {
+        Api::Cloud => serde_json::to_writer(&mut *out, &cloud(all_diags, overall_sev, source)),
+        Api::Server => serde_json::to_writer(&mut *out, &server(all_diags, overall_sev, source)),
+    }
+    .map_err(|e| Error::WriteOutput(e.into()))?;
+    out.write_all(b"\n").map_err(Error::WriteOutput)
+}
+
+pub fn cloud<'a>(
+    all_diags: &'a [rd::Diagnostic],
+    overall_sev: rd::Severity,
+    source: rd::Source<'a>,
+) -> Insights<CloudReport<'a>, CloudAnnotation<'a>> {
+    let report = CloudReport {
+        title: source.name,
+        details: details(all_diags, source),
+        reporter: "tfv2rd",
+        report_type: "BUG",
+        result: if overall_sev == rd::Severity::Error {
+            "FAILED"
+        } else {
+            "PASSED"
+        },
+        link: source.url,
+        data: data(all_diags),
+    };
+    let annotations = all_diags
+        .iter()
+        .zip(external_ids(all_diags))
+        .map(|(diag, external_id)| {
+            let (severity, annotation_type) = severity_and_type(diag);
+            CloudAnnotation {
+                external_id,
+                annotation_type,
+                path: &diag.location.path,
+                line: line(diag),
+                summary: message(diag),
+                details: diag.original_output.as_deref(),
+                severity,
+                link: link(diag),
+            }
+        })
+        .collect();
+    Insights {
+        report,
+        annotations,
+    }
+}
+
+pub fn server<'a>(
+    all_diags: &'a [rd::Diagnostic],
+    overall_sev: rd::Severity,
+    source: rd::Source<'a>,
+) -> Insights<ServerReport<'a>, ServerAnnotation<'a>> {
+    let report = ServerReport {
+        title: source.name,
+        details: details(all_diags, source),
+        reporter: "tfv2rd",
+        result: if overall_sev == rd::Severity::Error {
+            "FAIL"
+        } else {
+            "PASS"
+        },
+        link: source.url,
+        data: data(all_diags),
+    };
+    let annotations = all_diags
+        .iter()
+        .zip(external_ids(all_diags))
+        .map(|(diag, external_id)| {
+            let (severity, annotation_type) = severity_and_type(diag);
+            ServerAnnotation {
+                external_id,
+                path: &diag.location.path,
+                line: line(diag).unwrap_or(0),
+                message: message(diag),
+                severity,
+                annotation_type,
+                link: link(diag),
+            }
+        })
+        .collect();
+    Insights {
+        report,
+        annotations,
+    }
+}
+
+fn count(all_diags: &[rd::Diagnostic], severity: rd::Severity) -> u32 {
+    all_diags
+        .iter()
+        .filter(|d| d.severity == Some(severity))
+        .count() as u32
+}
+
+fn details(all_diags: &[rd::Diagnostic], source: rd::Source) -> String {
+    format!(
+        "{} found {} error(s) and {} warning(s).",
+        source.name,
+        count(all_diags, rd::Severity::Error),
+        count(all_diags, rd::Severity::Warning)
+    )
+}
+
+fn data(all_diags: &[rd::Diagnostic]) -> Vec<DataField> {
+    vec![
+        number("Errors", count(all_diags, rd::Severity::Error)),
+        number("Warnings", count(all_diags, rd::Severity::Warning)),
+        number("Total", all_diags.len() as u32),
+    ]
+}
+
+fn number(title: &'static str, value: u32) -> DataField {
+    DataField {
+        title,
+        data_type: "NUMBER",
+        value,
+    }
+}
+
+// Identifies the same problem across reports, so Bitbucket can track it as lines move.
+// Identical problems in the same file get a number to keep their ids unique.
+fn external_ids(all_diags: &[rd::Diagnostic]) -> Vec<String> {
+    let mut seen: HashMap<String, u32> = HashMap::new();
+    all_diags
+        .iter()
+        .map(|diag| {
+            let external_id = fingerprint(diag);
+            let n = seen.entry(external_id.clone()).or_default();
+            *n += 1;
+            if *n > 1 {
+                format!("{}-{}", external_id, n)
+            } else {
+                external_id
+            }
+        })
+        .collect()
+}
+
+fn severity_and_type(diag: &rd::Diagnostic) -> (&'static str, &'static str) {
+    match diag.severity {
+        Some(rd::Severity::Error) => ("HIGH", "BUG"),
+        Some(rd::Severity::Warning) => ("MEDIUM", "CODE_SMELL"),
+        _ => ("LOW", "CODE_SMELL"),
+    }
+}
+
+fn line(diag: &rd::Diagnostic) -> Option<u32> {
+    diag.location.range.as_ref().and_then(|r| r.start.line)
+}
+
+fn link<'a>(diag: &'a rd::Diagnostic) -> Option<&'a str> {
+    diag.code.as_ref().and_then(|c| c.url.as_deref())
+}
+
+fn message(diag: &rd::Diagnostic) -> String {
+    let mut message = diag.message.to_string();
+    if let Some(code) = &diag.code {
+        message = format!("{}: {}", code.value, message);
+    }
+    if message.chars().count() > MAX_MESSAGE {
+        message = message.chars().take(MAX_MESSAGE - 1).collect();
+        message.push('…');
+    }
+    message
+}
+
+// A fingerprint of a problem from its source, file, rule or message and the code it's
+// about, leaving out the line number so it stays the same when lines are added above.
+// FNV-1a is used rather than std's hasher as it must not change between builds.
+fn fingerprint(diag: &rd::Diagnostic) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut add = |text: &str| {
+        for b in text.bytes().chain(Some(0)) {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    add(diag.source.map_or("", |s| s.name));
+    add(&diag.location.path);
+    add(diag.code.as_ref().map_or(&diag.message, |c| &c.value));
+    if let Some(snippet) = &diag.snippet {
+        for line in snippet.lines() {
+            add(line.trim());
+        }
+    }
+    format!("tfv2rd-{:016x}", hash)
+}
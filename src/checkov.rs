@@ -0,0 +1,102 @@
+// Checkov JSON output definitions
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use crate::reviewdog as rd;
+use crate::scanner::{Finding, SeverityMap};
+
+// Checkov outputs one report per framework, as a list if more than one framework ran,
+// or only a summary of the counts when there was nothing to scan.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Report<'a> {
+    Scanned {
+        #[serde(borrow)]
+        results: Results<'a>,
+    },
+    // The counts aren't used, but are required so other JSON isn't taken for a report.
+    Empty {
+        #[serde(rename = "passed")]
+        _passed: u32,
+        #[serde(rename = "failed")]
+        _failed: u32,
+        #[serde(rename = "resource_count")]
+        _resource_count: u32,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Results<'a> {
+    #[serde(borrow, default)]
+    pub failed_checks: Vec<Check<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Check<'a> {
+    #[serde(borrow)]
+    pub check_id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub check_name: Cow<'a, str>,
+    // Relative to the scanned directory but with a leading '/', unless a single file was scanned.
+    #[serde(borrow)]
+    pub file_path: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub file_abs_path: Option<Cow<'a, str>>,
+    pub file_line_range: [u32; 2],
+    #[serde(borrow, default)]
+    pub resource: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    pub guideline: Option<Cow<'a, str>>,
+    // Only filled in when Checkov is connected to the Bridgecrew platform.
+    #[serde(borrow, default)]
+    pub severity: Option<Cow<'a, str>>,
+}
+
+// Parses either a single report or a list of reports.
+pub fn parse(input: &str) -> serde_json::Result<Vec<Report<'_>>> {
+    if input.trim_start().starts_with('[') {
+        serde_json::from_str(input)
+    } else {
+        serde_json::from_str(input).map(|report| vec![report])
+    }
+}
+
+// Gives the paths of files relative to the scanned directory, or with absolute_paths,
+// absolute paths where Checkov has them, for a path converter with a base directory
+// to make relative to that.
+pub fn findings<'a>(
+    reports: Vec<Report<'a>>,
+    severities: &SeverityMap,
+    absolute_paths: bool,
+) -> Vec<Finding<'a>> {
+    reports
+        .into_iter()
+        .filter_map(|report| match report {
+            Report::Scanned { results } => Some(results),
+            Report::Empty { .. } => None,
+        })
+        .flat_map(|results| results.failed_checks)
+        .map(|check| Finding {
+            filename: match check.file_abs_path.filter(|_| absolute_paths) {
+                Some(path) => path,
+                None => match check.file_path {
+                    Cow::Borrowed(path) => Cow::Borrowed(path.trim_start_matches('/')),
+                    Cow::Owned(path) => Cow::Owned(path.trim_start_matches('/').to_owned()),
+                },
+            },
+            start_line: check.file_line_range[0],
+            end_line: check.file_line_range[1],
+            message: check.check_name,
+            // Checks failed without a severity still fail the Checkov run
+            severity: check
+                .severity
+                .map_or(rd::Severity::Error, |s| severities.get(&s)),
+            rule_id: check.check_id,
+            url: check.guideline,
+            detail: check
+                .resource
+                .map(|resource| Cow::Owned(format!("Resource: {}", resource))),
+        })
+        .collect()
+}
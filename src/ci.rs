@@ -0,0 +1,26 @@
+// Information about the CI system tfv2rd is running in, from its environment variables
+use std::env;
+
+// A link to the log of the CI job tfv2rd is running in, if it can be worked out.
+pub fn job_url() -> Option<String> {
+    job_url_from(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+// Works out the CI job link from environment variables looked up with var.
+pub fn job_url_from(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    // GitHub Actions
+    if let (Some(server), Some(repository), Some(run_id)) = (
+        var("GITHUB_SERVER_URL"),
+        var("GITHUB_REPOSITORY"),
+        var("GITHUB_RUN_ID"),
+    ) {
+        return Some(format!(
+            "{}/{}/actions/runs/{}",
+            server.trim_end_matches('/'),
+            repository,
+            run_id
+        ));
+    }
+    // GitLab CI
+    var("CI_JOB_URL")
+}
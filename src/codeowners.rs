@@ -0,0 +1,281 @@
+// Finding who owns the files diagnostics are about, from a GitHub or GitLab CODEOWNERS file
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use log::debug;
+use path_absolutize::Absolutize;
+
+use crate::error::Error;
+use crate::reviewdog as rd;
+
+// Where GitHub and GitLab look for the file, in the order they look.
+const LOCATIONS: [&str; 4] = [
+    ".github/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+];
+
+pub struct CodeOwners {
+    // The absolute path of the directory the patterns are relative to.
+    base: PathBuf,
+    sections: Vec<Section>,
+}
+
+// A GitLab section, like "[Networking] @net-team", or the whole file for GitHub.
+// The owners of a file are those of the last rule matching it in each section.
+struct Section {
+    default_owners: Vec<String>,
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    pattern: Pattern,
+    owners: Vec<String>,
+}
+
+// A gitignore-style pattern, split into path segments.
+struct Pattern {
+    segments: Vec<String>,
+    // A pattern ending in "/" only matches directories, i.e. the files in them.
+    dir_only: bool,
+}
+
+// Reads the CODEOWNERS file of the repository at base.
+pub fn load(base: &Path) -> Result<CodeOwners, Error> {
+    let base = base.absolutize().map_err(|source| Error::ReadFile {
+        path: base.to_owned(),
+        source,
+    })?;
+    for location in LOCATIONS.iter() {
+        let path = base.join(location);
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                debug!("Reading code owners from {}", path.display());
+                return Ok(parse(&base, &text));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(source) => return Err(Error::ReadFile { path, source }),
+        }
+    }
+    Err(Error::ReadFile {
+        path: base.join("CODEOWNERS"),
+        source: io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no CODEOWNERS file in {}", LOCATIONS.join(", ")),
+        ),
+    })
+}
+
+pub fn parse(base: &Path, text: &str) -> CodeOwners {
+    let mut sections = vec![Section {
+        default_owners: Vec::new(),
+        rules: Vec::new(),
+    }];
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('^').unwrap_or(line).strip_prefix('[') {
+            // Skip the section name, and the number of approvals GitLab may require
+            let owners = header
+                .split_once(']')
+                .map_or("", |(_, rest)| rest.trim_start());
+            let owners = match owners.strip_prefix('[') {
+                Some(approvals) => approvals.split_once(']').map_or("", |(_, rest)| rest),
+                None => owners,
+            };
+            sections.push(Section {
+                default_owners: tokens(owners),
+                rules: Vec::new(),
+            });
+            continue;
+        }
+        let mut tokens = tokens(line).into_iter();
+        let pattern = match tokens.next() {
+            Some(pattern) => Pattern::new(&pattern),
+            None => continue,
+        };
+        let section = sections.last_mut().expect("There is always a section");
+        let mut owners: Vec<String> = tokens.collect();
+        if owners.is_empty() {
+            owners = section.default_owners.clone();
+        }
+        section.rules.push(Rule { pattern, owners });
+    }
+    CodeOwners {
+        base: base.to_owned(),
+        sections,
+    }
+}
+
+// Splits a line into words, allowing spaces escaped with "\" and ending at a comment.
+fn tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => token.extend(chars.next()),
+            '#' if token.is_empty() => break,
+            c if c.is_whitespace() => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+impl CodeOwners {
+    // The owners of a file, given relative to the base directory or as an absolute path.
+    pub fn owners(&self, path: &str) -> Vec<&str> {
+        let path = Path::new(path);
+        let relative = if path.is_absolute() {
+            match path.strip_prefix(&self.base) {
+                Ok(relative) => relative,
+                Err(_) => return Vec::new(),
+            }
+        } else {
+            path
+        };
+        let segments: Vec<Cow<str>> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect();
+
+        let mut owners: Vec<&str> = Vec::new();
+        for section in &self.sections {
+            let rule = section
+                .rules
+                .iter()
+                .rev()
+                .find(|rule| rule.pattern.matches(&segments));
+            for owner in rule.into_iter().flat_map(|rule| &rule.owners) {
+                if !owners.contains(&owner.as_str()) {
+                    owners.push(owner);
+                }
+            }
+        }
+        owners
+    }
+
+    // Records the owners of the file a diagnostic is about, for the outputs that name them.
+    pub fn attribute(&self, diag: &mut rd::Diagnostic) {
+        let owners = self
+            .owners(&diag.location.path)
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        diag.owners = Some(owners);
+    }
+}
+
+// A diagnostic's message with the owners of its file named at the end. Only outputs
+// meant to be read use it, as others identify problems by the message.
+pub fn owned_message<'d>(diag: &'d rd::Diagnostic) -> Cow<'d, str> {
+    match diag.owners.as_deref() {
+        Some(owners) if !owners.is_empty() => {
+            Cow::Owned(format!("{} (owned by {})", diag.message, owners.join(", ")))
+        }
+        _ => Cow::Borrowed(&diag.message),
+    }
+}
+
+// Names the owners of a diagnostic's file in its message, for the reviewdog formats.
+pub fn name_owners(mut diag: rd::Diagnostic) -> rd::Diagnostic {
+    if let Cow::Owned(message) = owned_message(&diag) {
+        diag.message = Cow::Owned(message);
+    }
+    diag
+}
+
+impl Pattern {
+    fn new(pattern: &str) -> Self {
+        let dir_only = pattern.ends_with('/');
+        let trimmed = pattern.trim_end_matches('/');
+        // As in gitignore, a pattern with a slash before its end is relative to the base
+        // directory, and otherwise matches at any depth
+        let anchored = trimmed.contains('/');
+        let mut segments: Vec<String> = trimmed
+            .trim_start_matches('/')
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned)
+            .collect();
+        if !anchored {
+            segments.insert(0, "**".to_owned());
+        }
+        Pattern { segments, dir_only }
+    }
+
+    // Whether the pattern matches a file, or one of the directories it's in.
+    fn matches(&self, path: &[Cow<str>]) -> bool {
+        (1..=path.len()).any(|len| {
+            (len < path.len() || !self.dir_only) && match_segments(&self.segments, &path[..len])
+        })
+    }
+}
+
+fn match_segments(pattern: &[String], path: &[Cow<str>]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..]))
+        }
+        Some((first, rest)) => {
+            !path.is_empty() && wildcard(first, &path[0]) && match_segments(rest, &path[1..])
+        }
+    }
+}
+
+// Matches a name against a pattern where "*" matches any characters and "?" any one.
+fn wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last "*" if the rest doesn't match
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Whether a diagnostic's file is owned by any of the given owners, ignoring case as
+// GitHub and GitLab do for user and team names.
+pub fn owned_by(diag: &rd::Diagnostic, wanted: &[String]) -> bool {
+    diag.owners.iter().flatten().any(|owner| {
+        wanted
+            .iter()
+            .any(|wanted| wanted.eq_ignore_ascii_case(owner))
+    })
+}
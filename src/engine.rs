@@ -0,0 +1,102 @@
+// The tool that produced the input: Terraform or its fork OpenTofu
+use std::str::FromStr;
+
+use crate::terraform as tf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Terraform,
+    Tofu,
+    // Work out which one from the input, assuming Terraform if it can't be told.
+    Auto,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terraform" => Ok(Engine::Terraform),
+            "tofu" | "opentofu" => Ok(Engine::Tofu),
+            "auto" => Ok(Engine::Auto),
+            _ => Err(format!("Unknown engine '{}'", s)),
+        }
+    }
+}
+
+impl Engine {
+    // Replaces Auto with the engine detected from the input, if any.
+    pub fn resolve(self, detected: Option<Engine>) -> Engine {
+        match self {
+            Engine::Auto => detected.unwrap_or(Engine::Terraform),
+            engine => engine,
+        }
+    }
+
+    // The name of the engine's executable.
+    pub fn binary(self) -> &'static str {
+        match self {
+            Engine::Tofu => "tofu",
+            Engine::Terraform | Engine::Auto => "terraform",
+        }
+    }
+
+    // A name for the output of one of the engine's commands, for the "source" of diagnostics.
+    pub fn source_name(self, command: Option<&str>) -> &'static str {
+        match (self, command) {
+            (Engine::Tofu, None) => "tofu",
+            (Engine::Tofu, Some("validate")) => "tofu validate",
+            (Engine::Tofu, Some("fmt")) => "tofu fmt",
+            (Engine::Tofu, Some("test")) => "tofu test",
+            (_, Some("validate")) => "terraform validate",
+            (_, Some("fmt")) => "terraform fmt",
+            (_, Some("test")) => "terraform test",
+            _ => "terraform",
+        }
+    }
+
+    // A link to the documentation of one of the engine's commands, e.g. "fmt".
+    pub fn docs_url(self, command: &str) -> String {
+        match self {
+            Engine::Tofu => format!("https://opentofu.org/docs/cli/commands/{}/", command),
+            Engine::Terraform | Engine::Auto => format!(
+                "https://developer.hashicorp.com/terraform/cli/commands/{}",
+                command
+            ),
+        }
+    }
+
+    // The major format_versions of the engine's JSON output that can be read.
+    // OpenTofu forked from Terraform 1.6, so never output the 0.x formats.
+    pub fn format_versions(self) -> &'static [&'static str] {
+        match self {
+            Engine::Tofu => &["1"],
+            Engine::Terraform | Engine::Auto => &["0", "1"],
+        }
+    }
+
+    pub fn supports_format_version(self, major: &str) -> bool {
+        self.format_versions().contains(&major)
+    }
+
+    // The engine for validate -json output. Neither Terraform's nor OpenTofu's says
+    // which tool produced it, so it's only known from --engine, and Auto means Terraform.
+    pub fn for_validate(self) -> Engine {
+        self.resolve(None)
+    }
+
+    // Detects the engine from the version message at the start of a JSON message stream.
+    pub fn detect_stream(message: &tf::StreamMessage) -> Option<Engine> {
+        if message.message_type != "version" {
+            return None;
+        }
+        if message.tofu.is_some()
+            || message.module.as_deref() == Some("tofu.ui")
+            || message.message.starts_with("OpenTofu")
+        {
+            Some(Engine::Tofu)
+        } else {
+            Some(Engine::Terraform)
+        }
+    }
+}
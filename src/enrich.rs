@@ -0,0 +1,112 @@
+// Filling in missing parts of Terraform diagnostics from the source files they refer to
+use std::borrow::Cow;
+
+use crate::sources::{self, SourceFiles};
+use crate::terraform as tf;
+
+// Completes a diagnostic's range and snippet using its source file, where
+// Terraform left them out, so the output highlights something meaningful.
+pub fn enrich(diag: &mut tf::Diagnostic, sources: &SourceFiles) {
+    let range = match diag.range.as_mut() {
+        Some(range) => range,
+        None => return,
+    };
+    if range.start.is_some() && range.end.is_some() && diag.snippet.is_some() {
+        return;
+    }
+    let text = match sources.get(range.filename.as_ref()) {
+        Some(text) => text,
+        None => return,
+    };
+
+    // Don't trust the byte offsets if the file isn't the one Terraform read
+    let known = range.start.as_ref().or(range.end.as_ref());
+    if !known.is_some_and(|pos| sources::position_matches(&text, pos)) {
+        return;
+    }
+
+    if range.end.is_none() {
+        range.end = range
+            .start
+            .as_ref()
+            .and_then(|start| token_end(&text, start));
+    } else if range.start.is_none() {
+        range.start = range.end.as_ref().and_then(|end| line_start(&text, end));
+    }
+
+    if diag.snippet.is_none() {
+        if let (Some(start), Some(end)) = (&range.start, &range.end) {
+            diag.snippet = snippet(&text, start, end);
+        }
+    }
+}
+
+// Finds the end of the token starting at a position: a quoted string, an
+// identifier or attribute reference, or otherwise the rest of the line.
+fn token_end(text: &str, start: &tf::SourcePosition) -> Option<tf::SourcePosition> {
+    let from = start.byte as usize;
+    let rest = text.get(from..)?;
+    let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+    let len = match line.chars().next() {
+        None => return None,
+        Some('"') => line[1..]
+            .char_indices()
+            .scan(false, |escaped, (i, c)| {
+                let end = !*escaped && c == '"';
+                *escaped = !*escaped && c == '\\';
+                Some((i, end))
+            })
+            .find(|&(_, end)| end)
+            .map_or(line.len(), |(i, _)| i + 2),
+        Some(c) if is_identifier_char(c) => line
+            .find(|c: char| !(is_identifier_char(c) || c == '.'))
+            .unwrap_or(line.len()),
+        Some(_) => line.trim_end().len(),
+    };
+    Some(tf::SourcePosition {
+        byte: (from + len) as u32,
+        line: start.line,
+        column: start.column + line[..len].chars().count() as u32,
+    })
+}
+
+// Finds the first non-blank character on the line of a position.
+fn line_start(text: &str, end: &tf::SourcePosition) -> Option<tf::SourcePosition> {
+    let before = text.get(..end.byte as usize)?;
+    let line_begin = before.rfind('\n').map_or(0, |i| i + 1);
+    let indent = before[line_begin..].len() - before[line_begin..].trim_start().len();
+    Some(tf::SourcePosition {
+        byte: (line_begin + indent) as u32,
+        line: end.line,
+        column: before[line_begin..line_begin + indent].chars().count() as u32 + 1,
+    })
+}
+
+// Synthesises a snippet of the whole lines between two positions, like Terraform's own.
+fn snippet<'a>(
+    text: &str,
+    start: &tf::SourcePosition,
+    end: &tf::SourcePosition,
+) -> Option<tf::Snippet<'a>> {
+    let (start_byte, end_byte) = (start.byte as usize, end.byte as usize);
+    if end_byte < start_byte {
+        return None;
+    }
+    let code_begin = text.get(..start_byte)?.rfind('\n').map_or(0, |i| i + 1);
+    let code_end = text
+        .get(end_byte..)?
+        .find('\n')
+        .map_or(text.len(), |i| end_byte + i);
+    let code = text[code_begin..code_end].trim_end_matches('\r');
+    Some(tf::Snippet {
+        context: None,
+        code: Cow::Owned(code.to_owned()),
+        start_line: start.line,
+        highlight_start_offset: (start_byte - code_begin) as u32,
+        highlight_end_offset: (end_byte.min(code_begin + code.len()) - code_begin) as u32,
+    })
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
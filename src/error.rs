@@ -0,0 +1,209 @@
+// Errors that can stop tfv2rd, with the exit code reported for each
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::engine::Engine;
+use crate::input::InputProblem;
+
+// Maximum number of characters of offending input to show around a parse error.
+const SNIPPET_WIDTH: usize = 60;
+
+#[derive(Debug)]
+pub enum Error {
+    // Reading the Terraform output from stdin failed.
+    ReadInput(io::Error),
+
+    // A file given in the options could not be read.
+    ReadFile {
+        path: PathBuf,
+        source: io::Error,
+    },
+
+    // A file given in the options could not be parsed.
+    ParseFile {
+        path: PathBuf,
+        source: serde_json::Error,
+        expected: &'static str,
+    },
+
+    // The input could not be parsed as the expected Terraform JSON output.
+    ParseInput {
+        source: serde_json::Error,
+        snippet: String,
+        expected: &'static str,
+    },
+
+    // The input isn't terraform validate -json output, for a recognised reason.
+    UnexpectedInput(InputProblem),
+
+    // The input isn't a well-formed unified diff.
+    MalformedDiff {
+        line: usize,
+        reason: String,
+    },
+
+    // The input declares a format_version this version of tfv2rd doesn't understand
+    // for the engine that produced it.
+    UnsupportedFormatVersion {
+        version: String,
+        engine: Engine,
+    },
+
+    // A filename from the input could not be converted using --workdir / --basedir.
+    PathConversion {
+        path: String,
+        reason: String,
+    },
+
+    // Writing the converted output failed.
+    WriteOutput(io::Error),
+
+    // A terraform command tfv2rd runs itself, e.g. in lsp mode, couldn't be started.
+    RunCommand {
+        command: String,
+        source: io::Error,
+    },
+}
+
+impl Error {
+    // Builds a ParseInput error for input that should have been terraform validate -json output.
+    pub fn parse(source: serde_json::Error, input: &str) -> Self {
+        Error::parse_as(source, input, "terraform validate -json")
+    }
+
+    // Builds a ParseInput error, capturing the part of the input the parser choked on.
+    // expected is the command that should have produced the input.
+    pub fn parse_as(source: serde_json::Error, input: &str, expected: &'static str) -> Self {
+        let snippet = snippet_at(input, source.line(), source.column());
+        Error::ParseInput {
+            source,
+            snippet,
+            expected,
+        }
+    }
+
+    // Exit codes follow the conventions of sysexits.h so CI scripts can tell
+    // bad input apart from a misconfigured invocation or a broken pipe.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::ParseInput { .. } => 65,               // EX_DATAERR
+            Error::UnexpectedInput(_) => 65,              // EX_DATAERR
+            Error::MalformedDiff { .. } => 65,            // EX_DATAERR
+            Error::ParseFile { .. } => 65,                // EX_DATAERR
+            Error::ReadInput(_) => 66,                    // EX_NOINPUT
+            Error::ReadFile { .. } => 66,                 // EX_NOINPUT
+            Error::WriteOutput(_) => 74,                  // EX_IOERR
+            Error::RunCommand { .. } => 69,               // EX_UNAVAILABLE
+            Error::UnsupportedFormatVersion { .. } => 76, // EX_PROTOCOL
+            Error::PathConversion { .. } => 78,           // EX_CONFIG
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ReadInput(e) => write!(f, "Can't read input from stdin: {}", e),
+            Error::ReadFile { path, source } => {
+                write!(f, "Can't read {}: {}", path.display(), source)
+            }
+            Error::ParseFile {
+                path,
+                source,
+                expected,
+            } => write!(
+                f,
+                "Can't parse {}: {} (it should be `{}` output)",
+                path.display(),
+                source,
+                expected
+            ),
+            Error::ParseInput {
+                source,
+                snippet,
+                expected,
+            } => {
+                let hint = match source.classify() {
+                    serde_json::error::Category::Eof if snippet.is_empty() => {
+                        format!("the input is empty, check that `{}` actually ran", expected)
+                    }
+                    serde_json::error::Category::Eof => {
+                        "the input ended unexpectedly, it may have been truncated".to_owned()
+                    }
+                    serde_json::error::Category::Syntax => {
+                        format!("the input is not JSON, make sure to run `{}`", expected)
+                    }
+                    serde_json::error::Category::Data => format!(
+                        "the input is JSON but not `{}` output, check which command's output is piped into tfv2rd",
+                        expected
+                    ),
+                    serde_json::error::Category::Io => "the input could not be read".to_owned(),
+                };
+                write!(f, "Can't parse input: {} ({})", source, hint)?;
+                if !snippet.is_empty() {
+                    write!(f, "\n  near: {}", snippet)?;
+                }
+                Ok(())
+            }
+            Error::UnexpectedInput(problem) => write!(f, "Can't parse input: {}", problem),
+            Error::MalformedDiff { line, reason } => write!(
+                f,
+                "Can't parse input as terraform fmt -diff output at line {}: {}",
+                line, reason
+            ),
+            Error::UnsupportedFormatVersion { version, engine } => {
+                let versions: Vec<String> = engine
+                    .format_versions()
+                    .iter()
+                    .map(|major| format!("{}.x", major))
+                    .collect();
+                write!(
+                    f,
+                    "Unsupported format_version {} in input, tfv2rd understands {} validate -json format versions {}",
+                    version,
+                    engine.binary(),
+                    versions.join(" and ")
+                )
+            }
+            Error::PathConversion { path, reason } => {
+                write!(f, "Can't convert path '{}': {}", path, reason)
+            }
+            Error::WriteOutput(e) => write!(f, "Can't write output: {}", e),
+            Error::RunCommand { command, source } => {
+                write!(f, "Can't run `{}`: {}", command, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ReadInput(e) | Error::WriteOutput(e) => Some(e),
+            Error::ReadFile { source, .. } | Error::RunCommand { source, .. } => Some(source),
+            Error::ParseInput { source, .. } | Error::ParseFile { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Extracts the text around a 1-based line and column of the input, for showing in error messages.
+fn snippet_at(input: &str, line: usize, column: usize) -> String {
+    let text = match input.lines().nth(line.saturating_sub(1)) {
+        Some(text) => text,
+        None => return String::new(),
+    };
+    let chars: Vec<char> = text.chars().collect();
+    let column = column.min(chars.len());
+    let start = column.saturating_sub(SNIPPET_WIDTH / 2);
+    let end = (start + SNIPPET_WIDTH).min(chars.len());
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < chars.len() {
+        snippet.push_str("...");
+    }
+    snippet.trim().to_owned()
+}
@@ -0,0 +1,260 @@
+// Conversion of terraform fmt -check -diff output into suggested formatting changes
+use std::borrow::Cow;
+
+use log::warn;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::reviewdog as rd;
+
+// A run of removed and/or added lines within a hunk of the diff.
+#[derive(Debug, Default)]
+struct Change<'a> {
+    // Line number in the original file where the change starts.
+    old_start: u32,
+    removed: Vec<&'a str>,
+    added: Vec<&'a str>,
+}
+
+impl<'a> Change<'a> {
+    fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty()
+    }
+
+    // The range of original lines this change replaces, and the text to replace them with.
+    fn suggestion(&self) -> (rd::Range, String) {
+        let position = |line, column| rd::Position {
+            line: Some(line),
+            column,
+        };
+        if self.removed.is_empty() {
+            // Insert the added lines before the line the change starts at
+            let at = position(self.old_start, Some(1));
+            let text = self.added.iter().map(|l| format!("{}\n", l)).collect();
+            (
+                rd::Range {
+                    start: at,
+                    end: Some(position(self.old_start, Some(1))),
+                },
+                text,
+            )
+        } else if self.added.is_empty() {
+            // Delete the removed lines including their line endings
+            let end_line = self.old_start + self.removed.len() as u32;
+            (
+                rd::Range {
+                    start: position(self.old_start, Some(1)),
+                    end: Some(position(end_line, Some(1))),
+                },
+                String::new(),
+            )
+        } else {
+            // Replace the removed lines linewise
+            let end_line = self.old_start + self.removed.len() as u32 - 1;
+            (
+                rd::Range {
+                    start: position(self.old_start, None),
+                    end: Some(position(end_line, None)),
+                },
+                self.added.join("\n"),
+            )
+        }
+    }
+}
+
+// The changes terraform fmt would make to one file.
+#[derive(Debug)]
+struct FileDiff<'a> {
+    path: &'a str,
+    changes: Vec<Change<'a>>,
+}
+
+// Converts terraform fmt -diff output to diagnostics with suggestions, one per changed block of lines.
+// Also returns how many changes were left out with skip_errors as their paths couldn't be converted.
+pub fn convert<'a>(
+    input: &'a str,
+    path_converter: &dyn Fn(&str) -> Result<String, Error>,
+    skip_errors: bool,
+    source: rd::Source<'a>,
+    engine: Engine,
+) -> Result<(Vec<rd::Diagnostic<'a>>, usize), Error> {
+    let message = format!("Incorrect formatting, run `{} fmt` to fix", engine.binary());
+    let docs_url = engine.docs_url("fmt");
+    let mut all_diags = Vec::new();
+    let mut skipped = 0;
+    for file in parse(input)? {
+        let path = match path_converter(file.path) {
+            Ok(path) => path,
+            Err(e) if skip_errors => {
+                warn!(
+                    "Formatting changes to {} will be ignored as the path can't be converted: {}",
+                    file.path, e
+                );
+                skipped += file.changes.len();
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for change in file.changes {
+            let (range, text) = change.suggestion();
+            all_diags.push(rd::Diagnostic {
+                message: Cow::Owned(message.clone()),
+                location: rd::Location {
+                    path: path.clone(),
+                    range: Some(change_range(&change)),
+                },
+                severity: Some(rd::Severity::Warning),
+                source: Some(source),
+                code: Some(rd::Code {
+                    value: Cow::Borrowed("fmt"),
+                    url: Some(Cow::Owned(docs_url.clone())),
+                }),
+                suggestions: vec![rd::Suggestion {
+                    range,
+                    text: Cow::Owned(text),
+                }],
+                original_output: None,
+                snippet: if change.removed.is_empty() {
+                    None
+                } else {
+                    Some(Cow::Owned(change.removed.join("\n")))
+                },
+                summary: None,
+                owners: None,
+                root_module: None,
+            });
+        }
+    }
+    Ok((all_diags, skipped))
+}
+
+// The lines a change should be reported against: the removed lines, or the
+// line the added lines go before.
+fn change_range(change: &Change) -> rd::Range {
+    let last = change.old_start + (change.removed.len() as u32).max(1) - 1;
+    rd::Range {
+        start: rd::Position {
+            line: Some(change.old_start),
+            column: None,
+        },
+        end: Some(rd::Position {
+            line: Some(last),
+            column: None,
+        }),
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<FileDiff<'_>>, Error> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    // Line number in the original file of the next line of the current hunk, and
+    // how many original and new lines of the hunk are still to come.
+    let mut old_line = 0;
+    let mut old_remaining = 0;
+    let mut new_remaining = 0;
+    let mut change = Change::default();
+
+    for (index, line) in input.lines().enumerate() {
+        let malformed = |reason: &str| Error::MalformedDiff {
+            line: index + 1,
+            reason: reason.to_owned(),
+        };
+        if old_remaining == 0 && new_remaining == 0 {
+            // Between hunks: only file headers, hunk headers and the list of filenames can appear
+            if let Some(path) = line.strip_prefix("--- ") {
+                finish_change(&mut files, &mut change);
+                files.push(FileDiff {
+                    path: path.strip_prefix("old/").unwrap_or(path),
+                    changes: Vec::new(),
+                });
+            } else if line.starts_with("@@ ") {
+                if files.is_empty() {
+                    return Err(malformed("hunk before any file header"));
+                }
+                let (old_start, old_len, new_len) =
+                    parse_hunk_header(line).ok_or_else(|| malformed("invalid hunk header"))?;
+                old_line = old_start;
+                old_remaining = old_len;
+                new_remaining = new_len;
+            }
+            continue;
+        }
+
+        match line.chars().next() {
+            Some('-') => {
+                if change.is_empty() {
+                    change.old_start = old_line;
+                }
+                change.removed.push(&line[1..]);
+                old_line += 1;
+                old_remaining = old_remaining
+                    .checked_sub(1)
+                    .ok_or_else(|| malformed("more lines than the hunk header says"))?;
+            }
+            Some('+') => {
+                if change.is_empty() {
+                    change.old_start = old_line;
+                }
+                change.added.push(&line[1..]);
+                new_remaining = new_remaining
+                    .checked_sub(1)
+                    .ok_or_else(|| malformed("more lines than the hunk header says"))?;
+            }
+            Some(' ') | None => {
+                finish_change(&mut files, &mut change);
+                old_line += 1;
+                old_remaining = old_remaining
+                    .checked_sub(1)
+                    .ok_or_else(|| malformed("more lines than the hunk header says"))?;
+                new_remaining = new_remaining
+                    .checked_sub(1)
+                    .ok_or_else(|| malformed("more lines than the hunk header says"))?;
+            }
+            // e.g. "\ No newline at end of file"
+            Some('\\') => {}
+            Some(_) => return Err(malformed("unexpected line in hunk")),
+        }
+        if old_remaining == 0 && new_remaining == 0 {
+            finish_change(&mut files, &mut change);
+        }
+    }
+    if old_remaining > 0 || new_remaining > 0 {
+        return Err(Error::MalformedDiff {
+            line: input.lines().count(),
+            reason: "the last hunk is incomplete".to_owned(),
+        });
+    }
+    Ok(files)
+}
+
+// Adds the current change, if there is one, to the last file.
+fn finish_change<'a>(files: &mut [FileDiff<'a>], change: &mut Change<'a>) {
+    if !change.is_empty() {
+        if let Some(file) = files.last_mut() {
+            file.changes.push(std::mem::take(change));
+        }
+    }
+}
+
+// Parses "@@ -l,s +l,s @@" into the original start line and the original and new lengths.
+fn parse_hunk_header(line: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = line.split_whitespace().skip(1);
+    let (old_start, old_len) = parse_hunk_range(parts.next()?.strip_prefix('-')?)?;
+    let (_, new_len) = parse_hunk_range(parts.next()?.strip_prefix('+')?)?;
+    // An empty original range gives the line before the insertion
+    Some((
+        if old_len == 0 {
+            old_start + 1
+        } else {
+            old_start
+        },
+        old_len,
+        new_len,
+    ))
+}
+
+fn parse_hunk_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
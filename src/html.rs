@@ -0,0 +1,266 @@
+// A self-contained HTML report of the diagnostics, for publishing as a CI artifact
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use crate::reviewdog as rd;
+use crate::summary::severity_name;
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2em; color: #1f2328; }
+h1 { font-size: 1.5em; }
+.filters { display: flex; gap: 1em; margin: 1em 0; flex-wrap: wrap; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; vertical-align: top; padding: 0.4em 0.6em; border-bottom: 1px solid #d0d7de; }
+th { background: #f6f8fa; }
+.severity { font-weight: bold; white-space: nowrap; }
+.ERROR .severity { color: #cf222e; }
+.WARNING .severity { color: #9a6700; }
+.INFO .severity { color: #0969da; }
+pre { background: #f6f8fa; padding: 0.6em; overflow-x: auto; }
+.detail { white-space: pre-wrap; }
+.kw { color: #cf222e; }
+.str { color: #0a3069; }
+.num { color: #0550ae; }
+.comment { color: #6e7781; font-style: italic; }
+"#;
+
+const SCRIPT: &str = r#"
+function applyFilters() {
+  var severity = document.getElementById("severity").value;
+  var rule = document.getElementById("rule").value;
+  var module = document.getElementById("module").value;
+  var file = document.getElementById("file").value.toLowerCase();
+  var shown = 0;
+  document.querySelectorAll("tbody tr").forEach(function (row) {
+    var visible = (!severity || row.dataset.severity === severity) &&
+      (!rule || row.dataset.rule === rule) &&
+      (!module || row.dataset.module === module) &&
+      row.dataset.file.toLowerCase().indexOf(file) !== -1;
+    row.hidden = !visible;
+    if (visible) shown++;
+  });
+  document.getElementById("shown").textContent = shown;
+}
+document.querySelectorAll(".filters select, .filters input").forEach(function (el) {
+  el.addEventListener("input", applyFilters);
+});
+"#;
+
+// HCL keywords highlighted in snippets.
+const KEYWORDS: [&str; 18] = [
+    "resource",
+    "data",
+    "variable",
+    "output",
+    "locals",
+    "module",
+    "provider",
+    "terraform",
+    "dynamic",
+    "content",
+    "for",
+    "in",
+    "if",
+    "for_each",
+    "count",
+    "true",
+    "false",
+    "null",
+];
+
+// Writes a single HTML page with a table of the diagnostics that can be filtered
+// by severity, rule code, module and file.
+pub fn write(
+    out: &mut dyn Write,
+    all_diags: &[rd::Diagnostic],
+    overall_sev: rd::Severity,
+    source: rd::Source,
+) -> io::Result<()> {
+    let title = format!("{} report", source.name);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(&title))?;
+    writeln!(out, "<style>{}</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", escape(&title))?;
+    write!(
+        out,
+        "<p>Overall severity: <b>{}</b>. Showing <span id=\"shown\">{}</span> of {} diagnostics.",
+        severity_name(Some(overall_sev)),
+        all_diags.len(),
+        all_diags.len()
+    )?;
+    if let Some(url) = source.url {
+        write!(out, " <a href=\"{}\">Full output</a>", escape(url))?;
+    }
+    writeln!(out, "</p>")?;
+
+    let severities: BTreeSet<&str> = all_diags
+        .iter()
+        .map(|d| severity_name(d.severity))
+        .collect();
+    let rules: BTreeSet<&str> = all_diags
+        .iter()
+        .filter_map(|d| d.code.as_ref().map(|c| c.value.as_ref()))
+        .collect();
+    let modules: BTreeSet<&str> = all_diags
+        .iter()
+        .filter_map(|d| d.root_module.as_deref())
+        .collect();
+    writeln!(out, "<div class=\"filters\">")?;
+    write_filter(out, "severity", "Severity", severities.iter().copied())?;
+    write_filter(out, "rule", "Rule", rules.iter().copied())?;
+    write_filter(out, "module", "Module", modules.iter().copied())?;
+    writeln!(
+        out,
+        "<label>File <input id=\"file\" type=\"search\" placeholder=\"Filter by path\"></label>"
+    )?;
+    writeln!(out, "</div>")?;
+
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<thead><tr><th>Severity</th><th>Rule</th><th>File</th><th>Module</th><th>Line</th><th>Message</th></tr></thead>"
+    )?;
+    writeln!(out, "<tbody>")?;
+    for diag in all_diags {
+        write_row(out, diag)?;
+    }
+    writeln!(out, "</tbody>")?;
+    writeln!(out, "</table>")?;
+    writeln!(out, "<script>{}</script>", SCRIPT)?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+fn write_filter<'v>(
+    out: &mut dyn Write,
+    id: &str,
+    label: &str,
+    values: impl Iterator<Item = &'v str>,
+) -> io::Result<()> {
+    write!(
+        out,
+        "<label>{} <select id=\"{}\"><option value=\"\">All</option>",
+        label, id
+    )?;
+    for value in values {
+        write!(out, "<option>{}</option>", escape(value))?;
+    }
+    writeln!(out, "</select></label>")
+}
+
+fn write_row(out: &mut dyn Write, diag: &rd::Diagnostic) -> io::Result<()> {
+    let severity = severity_name(diag.severity);
+    let rule = diag.code.as_ref().map_or("", |c| c.value.as_ref());
+    let path = &diag.location.path;
+    let module = diag.root_module.as_deref().unwrap_or_default();
+    writeln!(
+        out,
+        "<tr class=\"{}\" data-severity=\"{}\" data-rule=\"{}\" data-module=\"{}\" data-file=\"{}\">",
+        severity,
+        severity,
+        escape(rule),
+        escape(module),
+        escape(path)
+    )?;
+    writeln!(out, "<td class=\"severity\">{}</td>", severity)?;
+    match diag.code.as_ref().and_then(|c| c.url.as_ref()) {
+        Some(url) => writeln!(
+            out,
+            "<td><a href=\"{}\">{}</a></td>",
+            escape(url),
+            escape(rule)
+        )?,
+        None => writeln!(out, "<td>{}</td>", escape(rule))?,
+    }
+    writeln!(out, "<td>{}</td>", escape(path))?;
+    writeln!(out, "<td>{}</td>", escape(module))?;
+    let line = diag
+        .location
+        .range
+        .as_ref()
+        .and_then(|r| r.start.line)
+        .map_or_else(String::new, |line| line.to_string());
+    writeln!(out, "<td>{}</td>", line)?;
+
+    write!(out, "<td>")?;
+    if diag.original_output.is_none() && diag.snippet.is_none() {
+        write!(out, "{}", escape(&diag.message))?;
+    } else {
+        write!(out, "<details><summary>{}</summary>", escape(&diag.message))?;
+        if let Some(detail) = &diag.original_output {
+            write!(
+                out,
+                "<div class=\"detail\">{}</div>",
+                escape(detail.trim_end())
+            )?;
+        }
+        if let Some(snippet) = &diag.snippet {
+            write!(out, "<pre><code>{}</code></pre>", highlight(snippet))?;
+        }
+        write!(out, "</details>")?;
+    }
+    writeln!(out, "</td>")?;
+    writeln!(out, "</tr>")
+}
+
+// Marks up HCL code with spans for keywords, strings, numbers and comments.
+fn highlight(code: &str) -> String {
+    let mut html = String::with_capacity(code.len() * 2);
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let (len, class) = if c == '#' || rest.starts_with("//") {
+            (rest.find('\n').unwrap_or(rest.len()), Some("comment"))
+        } else if c == '"' {
+            (string_len(rest), Some("str"))
+        } else if c.is_ascii_digit() {
+            (
+                rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.'))
+                    .unwrap_or(rest.len()),
+                Some("num"),
+            )
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(rest.len());
+            (len, Some("kw").filter(|_| KEYWORDS.contains(&&rest[..len])))
+        } else {
+            (c.len_utf8(), None)
+        };
+        let (token, after) = rest.split_at(len);
+        match class {
+            Some(class) => html.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                class,
+                escape(token)
+            )),
+            None => html.push_str(&escape(token)),
+        }
+        rest = after;
+    }
+    html
+}
+
+// The length of the quoted string at the start of the text, up to the end of the line if it isn't closed.
+fn string_len(text: &str) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            '"' if !escaped => return i + 1,
+            '\n' => return i,
+            _ => escaped = !escaped && c == '\\',
+        }
+    }
+    text.len()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
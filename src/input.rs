@@ -0,0 +1,91 @@
+// Recognising stdin contents that aren't clean terraform validate -json output
+use std::fmt;
+
+use serde_json::value::RawValue;
+
+use crate::terraform as tf;
+
+// Log levels Terraform prefixes its TF_LOG lines with.
+const TF_LOG_LEVELS: [&str; 5] = ["[TRACE]", "[DEBUG]", "[INFO]", "[WARN]", "[ERROR]"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputProblem {
+    // terraform validate was run without -json.
+    HumanReadable,
+    // TF_LOG output was captured along with the JSON.
+    LogLines,
+    // The hashicorp/setup-terraform wrapper script added its own output.
+    WrapperOutput,
+    // Some other text surrounds a validate -json object.
+    SurroundingText,
+}
+
+impl fmt::Display for InputProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InputProblem::HumanReadable => "the input looks like human-readable terraform validate output, run `terraform validate -json` instead",
+            InputProblem::LogLines => "the input contains TF_LOG log lines, unset TF_LOG or send the log elsewhere with TF_LOG_PATH, or use --lenient to skip them",
+            InputProblem::WrapperOutput => "the input contains output from the setup-terraform wrapper, set `terraform_wrapper: false` in the workflow or use --lenient to skip it",
+            InputProblem::SurroundingText => "the input contains other text around the terraform validate -json output, use --lenient to skip it",
+        })
+    }
+}
+
+// Works out why some input that failed to parse isn't terraform validate -json output, if possible.
+pub fn diagnose(input: &str) -> Option<InputProblem> {
+    if find_validate_result(input).is_some() {
+        if input.lines().any(is_wrapper_line) {
+            Some(InputProblem::WrapperOutput)
+        } else if input.lines().any(is_log_line) {
+            Some(InputProblem::LogLines)
+        } else {
+            Some(InputProblem::SurroundingText)
+        }
+    } else if input.lines().any(is_human_readable_line) {
+        Some(InputProblem::HumanReadable)
+    } else {
+        None
+    }
+}
+
+// Scans the input for the first top-level JSON object that has the shape of a
+// terraform validate -json result, ignoring any text around it.
+pub fn find_validate_result(input: &str) -> Option<tf::ValidateResult<'_>> {
+    let mut pos = 0;
+    while let Some(offset) = input[pos..].find('{') {
+        let start = pos + offset;
+        let mut objects =
+            serde_json::Deserializer::from_str(&input[start..]).into_iter::<&RawValue>();
+        match objects.next() {
+            Some(Ok(raw)) => {
+                if let Ok(result) = serde_json::from_str(raw.get()) {
+                    return Some(result);
+                }
+                // Skip over the whole object, so nested objects aren't mistaken for top-level ones
+                pos = start + objects.byte_offset();
+            }
+            _ => pos = start + 1,
+        }
+    }
+    None
+}
+
+fn is_log_line(line: &str) -> bool {
+    // e.g. "2021-10-01T12:00:00.000Z [INFO]  Terraform version: 1.0.8"
+    line.split_whitespace()
+        .take(2)
+        .any(|word| TF_LOG_LEVELS.contains(&word))
+}
+
+fn is_wrapper_line(line: &str) -> bool {
+    line.starts_with("[command]")
+        || line.starts_with("::debug::")
+        || line.starts_with("::set-output")
+}
+
+fn is_human_readable_line(line: &str) -> bool {
+    let line = line.trim_start_matches(|c: char| c == '│' || c.is_whitespace());
+    line.starts_with("Success! The configuration is valid")
+        || line.starts_with("Error: ")
+        || line.starts_with("Warning: ")
+}
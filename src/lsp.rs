@@ -0,0 +1,451 @@
+// A Language Server Protocol server over stdio, so editors show the same
+// terraform validate diagnostics, and suggested fixes, as tfv2rd reports in CI
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::{debug, warn};
+use serde_json::{json, Value};
+
+use crate::error::Error;
+use crate::modules::{is_terraform_file, module_files, root_module, run_validate};
+use crate::reviewdog as rd;
+use crate::schema::{self, ProviderSchemas};
+use crate::sources::SourceFiles;
+use crate::{
+    check_format_version, enrich_and_convert, parse_validate_result, path_to_string,
+    validate_codes, Opt,
+};
+
+// JSON-RPC error codes from the LSP specification.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// Runs terraform validate -json in a root module directory, returning its output.
+pub type Validator = Box<dyn Fn(&Path) -> Result<String, Error>>;
+
+// A position in a document as LSP counts it: 0-based line, and UTF-16 code units into the line.
+type Position = (u64, u64);
+
+// A quick fix for a diagnostic that was published, for answering code action requests.
+struct Fix {
+    start: Position,
+    end: Position,
+    action: Value,
+}
+
+// LSP diagnostics and quick fixes, by document URI.
+type Published = HashMap<String, (Vec<Value>, Vec<Fix>)>;
+
+// The last validate output for a root module, with the modification times of its
+// files when it ran, so it's reused until one of them changes.
+struct Cached {
+    files: Vec<(PathBuf, SystemTime)>,
+    output: String,
+}
+
+pub struct Server<'o> {
+    opt: &'o Opt,
+    schema: Option<ProviderSchemas>,
+    validate: Validator,
+    cache: HashMap<PathBuf, Cached>,
+    // Documents that diagnostics were published for, by root module, so they can be
+    // cleared once fixed.
+    published: HashMap<PathBuf, Vec<String>>,
+    fixes: HashMap<String, Vec<Fix>>,
+    shutdown: bool,
+}
+
+// Serves LSP requests on stdin and stdout until the client exits.
+pub fn serve(opt: &Opt) -> Result<(), Error> {
+    let schema = opt
+        .provider_schema
+        .as_deref()
+        .map(schema::load)
+        .transpose()?;
+    let engine = opt.engine.resolve(None);
+    let mut server = Server::new(
+        opt,
+        schema,
+        Box::new(move |root| run_validate(engine, root)),
+    );
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    server.run(&mut stdin.lock(), &mut stdout.lock())
+}
+
+impl<'o> Server<'o> {
+    pub fn new(opt: &'o Opt, schema: Option<ProviderSchemas>, validate: Validator) -> Self {
+        Server {
+            opt,
+            schema,
+            validate,
+            cache: HashMap::new(),
+            published: HashMap::new(),
+            fixes: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    // Handles messages until the client sends exit or closes the input.
+    pub fn run(&mut self, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), Error> {
+        while let Some(body) = read_message(input)? {
+            let message: Value = match serde_json::from_slice(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("Ignoring an LSP message that is not JSON: {}", e);
+                    write_message(out, &error_response(&Value::Null, PARSE_ERROR, e))?;
+                    continue;
+                }
+            };
+            let method = message["method"].as_str().unwrap_or_default();
+            if method == "exit" {
+                break;
+            }
+            let id = message.get("id");
+            match (self.handle(method, &message["params"], out)?, id) {
+                (Some(result), Some(id)) => {
+                    write_message(out, &json!({"jsonrpc": "2.0", "id": id, "result": result}))?
+                }
+                (None, Some(id)) => write_message(
+                    out,
+                    &error_response(id, METHOD_NOT_FOUND, format!("Unknown method {}", method)),
+                )?,
+                // Notifications don't get a response
+                (_, None) => {}
+            }
+        }
+        if !self.shutdown {
+            warn!("The LSP client exited without shutting down first");
+        }
+        Ok(())
+    }
+
+    // Handles a request or notification, returning the result, or None if the method isn't known.
+    fn handle(
+        &mut self,
+        method: &str,
+        params: &Value,
+        out: &mut dyn Write,
+    ) -> Result<Option<Value>, Error> {
+        Ok(Some(match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": {"openClose": true, "change": 0, "save": true},
+                    "codeActionProvider": {"codeActionKinds": ["quickfix"]},
+                },
+                "serverInfo": {"name": "tfv2rd", "version": env!("CARGO_PKG_VERSION")},
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/didOpen" | "textDocument/didSave" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                match uri_path(uri) {
+                    Some(path) if is_terraform_file(&path) => self.check(&path, out)?,
+                    _ => debug!("Not validating {}", uri),
+                }
+                Value::Null
+            }
+            "textDocument/codeAction" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let range = &params["range"];
+                let (start, end) = match (position(&range["start"]), position(&range["end"])) {
+                    (Some(start), Some(end)) => (start, end),
+                    _ => return Ok(Some(json!([]))),
+                };
+                let actions: Vec<&Value> = self
+                    .fixes
+                    .get(uri)
+                    .into_iter()
+                    .flatten()
+                    .filter(|fix| fix.start <= end && start <= fix.end)
+                    .map(|fix| &fix.action)
+                    .collect();
+                json!(actions)
+            }
+            "initialized"
+            | "textDocument/didChange"
+            | "textDocument/didClose"
+            | "$/cancelRequest"
+            | "$/setTrace" => Value::Null,
+            _ => return Ok(None),
+        }))
+    }
+
+    // Validates the root module a file is in and publishes its diagnostics. Problems
+    // running terraform are shown to the user rather than stopping the server.
+    fn check(&mut self, file: &Path, out: &mut dyn Write) -> Result<(), Error> {
+        let root = root_module(file);
+        let diagnostics = self
+            .validate_output(&root)
+            .and_then(|output| self.diagnostics(&root, &output));
+        let mut diagnostics = match diagnostics {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                warn!("Can't validate {}: {}", root.display(), e);
+                return write_message(
+                    out,
+                    &json!({
+                        "jsonrpc": "2.0",
+                        "method": "window/showMessage",
+                        "params": {"type": 1, "message": format!("tfv2rd: {}", e)},
+                    }),
+                );
+            }
+        };
+
+        // Clear the diagnostics of documents that no longer have any
+        for uri in self.published.remove(&root).unwrap_or_default() {
+            diagnostics.entry(uri).or_default();
+        }
+        let mut uris: Vec<String> = diagnostics.keys().cloned().collect();
+        uris.sort();
+        for uri in &uris {
+            let (lsp_diags, fixes) = diagnostics.remove(uri).unwrap_or_default();
+            write_message(
+                out,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": {"uri": uri, "diagnostics": lsp_diags},
+                }),
+            )?;
+            self.fixes.insert(uri.clone(), fixes);
+        }
+        self.published.insert(root, uris);
+        Ok(())
+    }
+
+    fn validate_output(&mut self, root: &Path) -> Result<String, Error> {
+        let files = module_files(root);
+        if let Some(cached) = self.cache.get(root) {
+            if cached.files == files {
+                debug!("Using the cached validate output for {}", root.display());
+                return Ok(cached.output.clone());
+            }
+        }
+        let output = (self.validate)(root)?;
+        self.cache.insert(
+            root.to_owned(),
+            Cached {
+                files,
+                output: output.clone(),
+            },
+        );
+        Ok(output)
+    }
+
+    // Converts validate output for a root module to LSP diagnostics and quick fixes.
+    fn diagnostics(&self, root: &Path, output: &str) -> Result<Published, Error> {
+        let r = parse_validate_result(output, true)?;
+        let engine = self.opt.engine.for_validate();
+        check_format_version(&r, engine)?;
+        let mut tf_diags = r.diagnostics;
+        let sources = SourceFiles::new(Some(root.to_owned()));
+        let path_converter = |filename: &str| path_to_string(root.join(filename));
+        let mut all_diags = enrich_and_convert(
+            &mut tf_diags,
+            &path_converter,
+            &sources,
+            self.schema.as_ref(),
+            true,
+            self.opt.source(engine),
+        )?;
+        validate_codes(&mut all_diags, engine);
+
+        let mut texts: HashMap<String, Option<String>> = HashMap::new();
+        let mut by_uri = Published::new();
+        for diag in &all_diags {
+            let path = &diag.location.path;
+            let text = texts
+                .entry(path.clone())
+                .or_insert_with(|| fs::read_to_string(path).ok());
+            let (start, end) = match &diag.location.range {
+                Some(range) => lsp_range(text.as_deref(), range),
+                None => ((0, 0), (0, 0)),
+            };
+            let lsp_diag = lsp_diagnostic(diag, start, end);
+            let uri = file_uri(Path::new(path));
+            let entry = by_uri.entry(uri.clone()).or_default();
+            for suggestion in &diag.suggestions {
+                let (fix_start, fix_end) = lsp_range(text.as_deref(), &suggestion.range);
+                entry.1.push(Fix {
+                    start,
+                    end,
+                    action: json!({
+                        "title": fix_title(&suggestion.text),
+                        "kind": "quickfix",
+                        "diagnostics": [lsp_diag],
+                        "isPreferred": true,
+                        "edit": {"changes": {uri.clone(): [{
+                            "range": range_json(fix_start, fix_end),
+                            "newText": suggestion.text,
+                        }]}},
+                    }),
+                });
+            }
+            entry.0.push(lsp_diag);
+        }
+        Ok(by_uri)
+    }
+}
+
+fn lsp_diagnostic(diag: &rd::Diagnostic, start: Position, end: Position) -> Value {
+    let mut message = diag.message.to_string();
+    if let Some(detail) = &diag.original_output {
+        message.push_str("\n\n");
+        message.push_str(detail.trim_end());
+    }
+    let mut lsp_diag = json!({
+        "range": range_json(start, end),
+        "message": message,
+    });
+    let severity = match diag.severity {
+        Some(rd::Severity::Error) => Some(1),
+        Some(rd::Severity::Warning) => Some(2),
+        Some(rd::Severity::Info) => Some(3),
+        Some(rd::Severity::UnknownSeverity) | None => None,
+    };
+    if let Some(severity) = severity {
+        lsp_diag["severity"] = json!(severity);
+    }
+    if let Some(source) = &diag.source {
+        lsp_diag["source"] = json!(source.name);
+    }
+    if let Some(code) = &diag.code {
+        lsp_diag["code"] = json!(code.value);
+        if let Some(url) = &code.url {
+            lsp_diag["codeDescription"] = json!({ "href": url });
+        }
+    }
+    lsp_diag
+}
+
+fn fix_title(text: &str) -> String {
+    match text.lines().count() {
+        0 => "Remove".to_owned(),
+        1 => format!("Change to {}", text),
+        _ => "Apply suggested fix".to_owned(),
+    }
+}
+
+// Converts a Reviewdog range, with 1-based lines and byte columns, to LSP positions.
+// A range without an end covers just its start.
+fn lsp_range(text: Option<&str>, range: &rd::Range) -> (Position, Position) {
+    let start = lsp_position(text, &range.start);
+    let end = range
+        .end
+        .as_ref()
+        .map_or(start, |end| lsp_position(text, end));
+    (start, end)
+}
+
+fn lsp_position(text: Option<&str>, pos: &rd::Position) -> Position {
+    let line = pos.line.unwrap_or(1).saturating_sub(1);
+    let bytes = pos.column.unwrap_or(1).saturating_sub(1) as usize;
+    let character = match text.and_then(|text| text.lines().nth(line as usize)) {
+        Some(line_text) => {
+            let before = line_text
+                .get(..bytes.min(line_text.len()))
+                .unwrap_or(line_text);
+            before.encode_utf16().count()
+        }
+        // Without the file assume the line is ASCII
+        None => bytes,
+    };
+    (u64::from(line), character as u64)
+}
+
+fn range_json(start: Position, end: Position) -> Value {
+    json!({
+        "start": {"line": start.0, "character": start.1},
+        "end": {"line": end.0, "character": end.1},
+    })
+}
+
+fn position(value: &Value) -> Option<Position> {
+    Some((value["line"].as_u64()?, value["character"].as_u64()?))
+}
+
+fn error_response(id: &Value, code: i64, message: impl ToString) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message.to_string()},
+    })
+}
+
+// Reads the body of an LSP message, which has HTTP-style headers giving its length,
+// or returns None at the end of the input.
+fn read_message(input: &mut dyn BufRead) -> Result<Option<Vec<u8>>, Error> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line).map_err(Error::ReadInput)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        Error::ReadInput(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "an LSP message has no Content-Length header",
+        ))
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).map_err(Error::ReadInput)?;
+    Ok(Some(body))
+}
+
+fn write_message(out: &mut dyn Write, message: &Value) -> Result<(), Error> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body).map_err(Error::WriteOutput)?;
+    out.flush().map_err(Error::WriteOutput)
+}
+
+// The path of a file: URI, decoding any percent-encoded bytes.
+pub fn uri_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&b, after)) = rest.split_first() {
+        match (b, after.get(..2)) {
+            (b'%', Some(hex)) => {
+                let hex = std::str::from_utf8(hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &after[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = after;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+// The file: URI of an absolute path, percent-encoding all but unreserved characters.
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = "file://".to_owned();
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
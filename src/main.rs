@@ -1,23 +1,69 @@
 use std::borrow::{Borrow, Cow, ToOwned};
-use std::io::{self, Read, Write};
+use std::io::{self, BufRead, Read, Write};
 use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
+use std::time::Duration;
 
 use log::warn;
 use path_absolutize::Absolutize;
 use pathdiff::diff_paths;
-use rd::Source;
 use structopt::StructOpt;
 
+mod azure;
+mod bitbucket;
+mod checkov;
+mod ci;
+mod codeowners;
+mod engine;
+mod enrich;
+mod error;
+mod fmtdiff;
+mod html;
+mod input;
+mod lsp;
+mod markdown;
+mod modules;
+mod plain;
+mod protobuf;
 mod reviewdog;
+mod scanner;
+mod schema;
+mod sonarqube;
+mod sources;
+mod suggest;
+mod summary;
 mod terraform;
+mod terragrunt;
+mod tfsec;
+mod tftest;
+mod trivy;
+mod watch;
+use azure::FailOn;
+use codeowners::CodeOwners;
+use engine::Engine;
+use error::Error;
 use reviewdog as rd;
+use scanner::SeverityMap;
+use schema::ProviderSchemas;
+use sources::SourceFiles;
+use summary::Summary;
 use terraform as tf;
+use tftest::TestResults;
 
 #[derive(Debug)]
 enum OutputFormat {
     RdJson,
     RdJsonL,
+    Markdown,
+    Html,
+    Bitbucket,
+    BitbucketServer,
+    Azure,
+    SonarQube,
+    Plain,
+    RdPb,
+    RdPbStream,
 }
 
 impl FromStr for OutputFormat {
@@ -27,15 +73,75 @@ impl FromStr for OutputFormat {
         match s {
             "rdjson" => Ok(OutputFormat::RdJson),
             "rdjsonl" => Ok(OutputFormat::RdJsonL),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            "bitbucket" => Ok(OutputFormat::Bitbucket),
+            "bitbucket-server" => Ok(OutputFormat::BitbucketServer),
+            "azure" => Ok(OutputFormat::Azure),
+            "sonarqube" => Ok(OutputFormat::SonarQube),
+            "plain" => Ok(OutputFormat::Plain),
+            "rdpb" => Ok(OutputFormat::RdPb),
+            "rdpb-stream" => Ok(OutputFormat::RdPbStream),
             _ => Err(format!("Unknown output format '{}'", s)),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum InputFormat {
+    Validate,
+    Stream,
+    FmtDiff,
+    Tfsec,
+    Trivy,
+    Checkov,
+    Test,
+    Terragrunt,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "validate" => Ok(InputFormat::Validate),
+            "stream" => Ok(InputFormat::Stream),
+            "fmt-diff" => Ok(InputFormat::FmtDiff),
+            "tfsec" => Ok(InputFormat::Tfsec),
+            "trivy" => Ok(InputFormat::Trivy),
+            "checkov" => Ok(InputFormat::Checkov),
+            "test" => Ok(InputFormat::Test),
+            "terragrunt" => Ok(InputFormat::Terragrunt),
+            _ => Err(format!("Unknown input format '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Run a Language Server Protocol server on stdin and stdout, which runs terraform validate in the root module of each .tf file that's opened or saved and publishes the diagnostics, with quick fixes for those that have suggestions. Other options, like --engine and --provider-schema, apply as when converting.
+    Lsp,
+
+    /// Watch a directory for changes to .tf and .tfvars files, re-running terraform validate in the root modules they're in and reprinting the diagnostics in the --format given.
+    Watch {
+        #[structopt(parse(from_os_str))]
+        /// Directory to watch, including the directories below it.
+        dir: PathBuf,
+
+        #[structopt(short, long, parse(from_os_str))]
+        /// Rewrite this file with the output after each validation, instead of printing it.
+        output: Option<PathBuf>,
+
+        #[structopt(long, default_value = "300")]
+        /// How long to wait, in milliseconds, for files to stop changing before validating them.
+        debounce: u64,
+    },
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(
     name="tfv2rd",
-    about="Converts terraform validate JSON output to Reviewdog Diagnostic Format.",
+    about="Converts terraform validate JSON output, and output of related tools, to Reviewdog Diagnostic Format.",
     version=env!("CARGO_PKG_VERSION")
 )]
 struct Opt {
@@ -51,71 +157,154 @@ struct Opt {
     /// Omit diagnostics in the output if errors are encountered converting them to Reviewdog format, instead of exiting with an error.
     skip_errors: bool,
 
+    #[structopt(short, long, default_value = "validate")]
+    /// Format of the input, either validate (terraform validate -json output, default), stream (the line-delimited JSON messages from terraform plan -json or apply -json), test (terraform test -json output, with a summary of failed runs per test file), terragrunt (terragrunt run-all validate -json output, with filenames mapped back to each unit's module sources), fmt-diff (terraform fmt -check -diff output, converted to suggested changes), tfsec (tfsec --format json output), trivy (trivy config --format json output) or checkov (checkov -o json output).
+    input: InputFormat,
+
+    #[structopt(long)]
+    /// Look for the terraform validate JSON output anywhere in the input, ignoring log lines or other text around it.
+    lenient: bool,
+
     #[structopt(short, long, default_value = "rdjsonl")]
-    /// Format for output, either rdjsonl (one JSON Diagnostic object per line, default) or rdjson (a single RdJSON object).
+    /// Format for output, either rdjsonl (one JSON Diagnostic object per line, default), rdjson (a single RdJSON object), markdown (a report grouped by file, e.g. for $GITHUB_STEP_SUMMARY or a merge request comment), html (a single page with a filterable table, e.g. to publish as a CI artifact), bitbucket (a JSON object with the Bitbucket Cloud Code Insights "report" and "annotations" to send to its API), bitbucket-server (the same for the Code Insights API of Bitbucket Server and Data Center), azure (Azure Pipelines logging commands annotating the build and setting the task result), sonarqube (SonarQube generic issue data, for sonar.externalIssuesReportPaths), plain (compiler-style "path:line:col: severity: message" lines, for editors' quickfix lists and reviewdog -efm), rdpb (a single length-delimited protobuf DiagnosticResult message, as defined in reviewdog's .proto) or rdpb-stream (a length-delimited protobuf Diagnostic message per diagnostic).
     format: OutputFormat,
 
-    #[structopt(short, long, default_value = "terraform validate")]
-    /// Value for "source" of the diagnostics to report in the output.
-    source: String,
+    #[structopt(long)]
+    /// With --format plain, give the end of each diagnostic's range too, as "path:line:col-line:col".
+    end_position: bool,
+
+    #[structopt(long, default_value = "error")]
+    /// With --format azure, the least severe diagnostics that make the task fail, either error (default), warning, info or never. The task succeeds with issues if there are only less severe diagnostics.
+    fail_on: FailOn,
+
+    #[structopt(long, default_value = "auto")]
+    /// Which tool produced the input, either terraform, tofu (OpenTofu) or auto (detect it from the input, default). terraform validate output doesn't say which tool produced it, so auto takes it as terraform's. Affects the default source name, documentation links and which output versions are accepted.
+    engine: Engine,
+
+    #[structopt(long, parse(from_os_str))]
+    /// File containing `terraform providers schema -json` output, used to suggest corrections for mistyped argument and block names.
+    provider_schema: Option<PathBuf>,
+
+    #[structopt(long)]
+    /// How to map tfsec, Trivy and Checkov severity levels to Reviewdog severities, as a list like "HIGH=warning,LOW=info". By default CRITICAL and HIGH are errors, MEDIUM is a warning and LOW is info.
+    severity_map: Option<SeverityMap>,
+
+    #[structopt(long)]
+    /// Read the CODEOWNERS file (in GitHub or GitLab syntax) of the base directory, or the current directory without --basedir, and name the owners of each diagnostic's file at the end of its message in the rdjson, rdjsonl, markdown and plain formats.
+    codeowners: bool,
+
+    #[structopt(long, number_of_values = 1)]
+    /// Only output diagnostics for files owned by this user, team or email address in CODEOWNERS, e.g. "@org/network-team". Can be given more than once. Implies --codeowners.
+    owner: Vec<String>,
+
+    #[structopt(short, long)]
+    /// Value for "source" of the diagnostics to report in the output. Defaults to the terraform command that produced the input.
+    source: Option<String>,
+
+    #[structopt(long)]
+    /// With --input test, don't print terraform test's summary of the whole run, e.g. "terraform test fail: 2 passed, 1 failed, 0 errored, 0 skipped", on stderr.
+    no_test_summary: bool,
+
+    #[structopt(long, parse(from_os_str))]
+    /// Write a summary of the conversion to this file, or to stderr for "-": counts of diagnostics by severity, rule code, file and root module, how many were left out and why, and how they compare with the counts terraform validate reported.
+    summary: Option<PathBuf>,
+
+    #[structopt(long)]
+    /// URL for the "source" of the diagnostics, such as a link to the full log of the CI job. Defaults to the GitHub Actions run or GitLab CI job tfv2rd is running in, if any.
+    source_url: Option<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+impl Opt {
+    // The "source" of the diagnostics, which is given to each diagnostic as well as the whole result.
+    fn source(&self, engine: Engine) -> rd::Source<'_> {
+        rd::Source {
+            name: self.source_name(engine),
+            url: self.source_url.as_deref().filter(|url| !url.is_empty()),
+        }
+    }
+
+    fn source_name(&self, engine: Engine) -> &str {
+        match (&self.source, self.input) {
+            (Some(source), _) => source,
+            (None, InputFormat::Validate) => engine.source_name(Some("validate")),
+            (None, InputFormat::Stream) => engine.source_name(None),
+            (None, InputFormat::FmtDiff) => engine.source_name(Some("fmt")),
+            (None, InputFormat::Test) => engine.source_name(Some("test")),
+            (None, InputFormat::Tfsec) => "tfsec",
+            (None, InputFormat::Trivy) => "trivy",
+            (None, InputFormat::Checkov) => "checkov",
+            (None, InputFormat::Terragrunt) => "terragrunt validate",
+        }
+    }
 }
 
 fn convert<'a>(
-    tf_result: &'a tf::ValidateResult,
-    path_converter: &dyn Fn(&str) -> io::Result<String>,
+    tf_diags: &'a [tf::Diagnostic],
+    path_converter: &dyn Fn(&str) -> Result<String, Error>,
+    sources: &SourceFiles,
+    schema: Option<&ProviderSchemas>,
     skip_errors: bool,
-    source: &'a str,
-) -> io::Result<Vec<reviewdog::Diagnostic<'a>>> {
-    let rd_diags_iter = tf_result
-        .diagnostics
+    source: rd::Source<'a>,
+) -> Result<Vec<reviewdog::Diagnostic<'a>>, Error> {
+    tf_diags
         .iter()
-        .filter(|diag| {
-            if diag.range.is_none() {
-                warn!("The TF {} {} has no source file location and cannot be reported as RdJSON, it will be ignored.", diag.severity, diag.summary);
-                false
-            } else {
-                true
-            }
+        .filter_map(|diag| {
+            convert_or_skip(diag, path_converter, sources, schema, skip_errors, source).transpose()
         })
-        .map(|diag| convert_one_diag(diag, path_converter, source));
-
-    if skip_errors {
-        rd_diags_iter
-            .filter(|r| {
-                if let Err(e) = r {
-                    warn!(
-                        "A TF diagnostic could not be converted and will be ignored: {}",
-                        e
-                    );
-                    false
-                } else {
-                    true
-                }
-            })
-            .collect::<io::Result<_>>()
-    } else {
-        rd_diags_iter.collect::<io::Result<_>>()
+        .collect()
+}
+
+// Converts a single TF diagnostic, or returns None if it should be left out of the output.
+fn convert_or_skip<'a>(
+    diag: &'a tf::Diagnostic,
+    path_converter: &dyn Fn(&str) -> Result<String, Error>,
+    sources: &SourceFiles,
+    schema: Option<&ProviderSchemas>,
+    skip_errors: bool,
+    source: rd::Source<'a>,
+) -> Result<Option<rd::Diagnostic<'a>>, Error> {
+    if diag.range.is_none() {
+        warn!("The TF {} {} has no source file location and cannot be reported as RdJSON, it will be ignored.", diag.severity, diag.summary);
+        return Ok(None);
+    }
+    match convert_one_diag(diag, path_converter, sources, schema, source) {
+        Err(e) if skip_errors => {
+            warn!(
+                "A TF diagnostic could not be converted and will be ignored: {}",
+                e
+            );
+            Ok(None)
+        }
+        result => result.map(Some),
     }
 }
 
 fn convert_one_diag<'a>(
     diag: &'a tf::Diagnostic,
-    path_converter: &dyn Fn(&str) -> Result<String, io::Error>,
-    source: &'a str,
-) -> Result<rd::Diagnostic<'a>, io::Error> {
+    path_converter: &dyn Fn(&str) -> Result<String, Error>,
+    sources: &SourceFiles,
+    schema: Option<&ProviderSchemas>,
+    source: rd::Source<'a>,
+) -> Result<rd::Diagnostic<'a>, Error> {
     let has_range = diag.range.as_ref().unwrap();
+    let file = sources.get(has_range.filename.as_ref());
+    let column = |pos| sources::byte_column(pos, file.as_deref(), diag.snippet.as_ref());
+    let suggestions = suggest::suggestions(diag, file.as_deref(), schema);
     Ok(rd::Diagnostic {
-        message: diag.summary,
+        message: suggest::message(diag, &suggestions),
         location: rd::Location {
             path: path_converter(has_range.filename.as_ref())?,
             range: has_range.start.as_ref().map(|has_start| rd::Range {
                 start: rd::Position {
                     line: Some(has_start.line),
-                    column: Some(has_start.column),
+                    column: Some(column(has_start)),
                 },
                 end: has_range.end.as_ref().map(|has_end| rd::Position {
                     line: Some(has_end.line),
-                    column: Some(has_end.column),
+                    column: Some(column(has_end)),
                 }),
             }),
         },
@@ -125,98 +314,669 @@ fn convert_one_diag<'a>(
             "info" => rd::Severity::Info,
             _ => rd::Severity::UnknownSeverity,
         }),
-        source: Some(rd::Source {
-            name: source,
-            url: None,
-        }),
+        source: Some(source),
         code: None,
-        suggestions: Vec::new(),
-        original_output: diag.detail,
+        suggestions,
+        original_output: diag.detail.as_deref().map(Cow::Borrowed),
+        snippet: diag
+            .snippet
+            .as_ref()
+            .map(|s| Cow::Borrowed(s.code.as_ref())),
+        summary: Some(Cow::Borrowed(&diag.summary)),
+        owners: None,
+        root_module: None,
     })
 }
 
-fn path_to_string(pb: PathBuf) -> io::Result<String> {
-    pb.into_os_string().into_string().map_err(|bad_path| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            format!("Can't encode path {:?} as UTF-8", bad_path),
-        )
-    })
+fn path_to_string(pb: PathBuf) -> Result<String, Error> {
+    pb.into_os_string()
+        .into_string()
+        .map_err(|bad_path| Error::PathConversion {
+            path: bad_path.to_string_lossy().into_owned(),
+            reason: "it can't be encoded as UTF-8".to_owned(),
+        })
 }
 
 fn path_fn<F>(f: F) -> F
 where
-    F: for<'a> Fn(&'a str) -> io::Result<Cow<'a, Path>>,
+    F: for<'a> Fn(&'a str) -> Result<Cow<'a, Path>, Error>,
 {
     f
 }
 
-fn main() -> io::Result<()> {
+fn check_format_version(tf_result: &tf::ValidateResult, engine: Engine) -> Result<(), Error> {
+    let raw = tf_result.format_version.get();
+    let unsupported = || Error::UnsupportedFormatVersion {
+        version: raw.to_owned(),
+        engine,
+    };
+    let version: String = serde_json::from_str(raw).map_err(|_| unsupported())?;
+    match version.split('.').next() {
+        Some(major) if engine.supports_format_version(major) => Ok(()),
+        _ => Err(unsupported()),
+    }
+}
+
+fn parse_validate_result(input: &str, lenient: bool) -> Result<tf::ValidateResult<'_>, Error> {
+    if lenient {
+        if let Some(r) = input::find_validate_result(input) {
+            return Ok(r);
+        }
+    }
+    serde_json::from_str(input).map_err(|e| match input::diagnose(input) {
+        Some(problem) => Error::UnexpectedInput(problem),
+        None => Error::parse(e, input),
+    })
+}
+
+// Parses the validate -json output of each unit in terragrunt run-all output, with
+// filenames made relative to the directory terragrunt was run in.
+fn parse_run_all<'a>(
+    units: &'a [terragrunt::UnitOutput],
+    opt: &Opt,
+) -> Result<Vec<(&'a terragrunt::UnitOutput, tf::ValidateResult<'a>)>, Error> {
+    let base = opt.workdir.as_deref().unwrap_or_else(|| Path::new(""));
+    let sources = terragrunt::Sources::default();
+    let mut results = Vec::new();
+    for unit in units {
+        // Without line prefixes the output of every unit is run together
+        let objects = serde_json::Deserializer::from_str(&unit.text).into_iter();
+        for r in objects {
+            let mut r: tf::ValidateResult =
+                r.map_err(|e| Error::parse_as(e, &unit.text, "terragrunt run-all validate -json"))?;
+            if let Some(dir) = &unit.dir {
+                for range in r.diagnostics.iter_mut().filter_map(|d| d.range.as_mut()) {
+                    range.filename =
+                        Cow::Owned(terragrunt::unit_file(base, dir, &range.filename, &sources));
+                }
+            }
+            results.push((unit, r));
+        }
+    }
+    Ok(results)
+}
+
+fn main() {
     pretty_env_logger::init();
-    let opt = Opt::from_args();
-    let path_converter: PathConverter = make_path_converter(&opt)?;
+    let mut opt = Opt::from_args();
+    if opt.source_url.is_none() {
+        opt.source_url = ci::job_url();
+    }
+    let result = match &opt.command {
+        Some(Command::Lsp) => lsp::serve(&opt),
+        Some(Command::Watch {
+            dir,
+            output,
+            debounce,
+        }) => watch::watch(
+            &opt,
+            dir,
+            output.as_deref(),
+            Duration::from_millis(*debounce),
+        ),
+        None => run(&opt),
+    };
+    if let Err(e) = result {
+        eprintln!("tfv2rd: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(opt: &Opt) -> Result<(), Error> {
+    let path_converter: PathConverter = make_path_converter(opt)?;
+    let sources = SourceFiles::new(opt.workdir.clone());
+    let schema = opt
+        .provider_schema
+        .as_deref()
+        .map(schema::load)
+        .transpose()?;
+    let codeowners = load_codeowners(opt)?;
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    if let (InputFormat::Stream | InputFormat::Test, OutputFormat::RdJsonL) =
+        (opt.input, &opt.format)
+    {
+        // Each diagnostic can be written out as soon as it's read
+        let summary = stream_rdjsonl(
+            &mut stdin.lock(),
+            &mut stdout.lock(),
+            opt,
+            &path_converter,
+            &sources,
+            schema.as_ref(),
+            codeowners.as_ref(),
+        )?;
+        return write_summary(opt, summary, &mut io::stderr());
+    }
 
     let mut input = String::with_capacity(128);
-    io::stdin().read_to_string(&mut input)?;
-    let r: tf::ValidateResult = serde_json::from_str(input.as_str())?;
-    let all_diags = convert(&r, &path_converter, opt.skip_errors, opt.source.as_str())?;
-
-    match opt.format {
-        OutputFormat::RdJson => {
-            let overall_sev = if r.error_count > 0 {
-                rd::Severity::Error
-            } else if r.warning_count > 0 {
-                rd::Severity::Warning
-            } else {
-                rd::Severity::Info
-            };
-            serde_json::to_writer(
-                io::stdout(),
-                &rd::DiagnosticResult {
-                    diagnostics: all_diags,
-                    severity: Some(overall_sev),
-                    source: Some(Source {
-                        name: opt.source.as_str(),
-                        url: None,
-                    }),
-                },
-            )?
+    stdin
+        .lock()
+        .read_to_string(&mut input)
+        .map_err(Error::ReadInput)?;
+    let units;
+    let mut unit_diags: Vec<(Option<&str>, Vec<tf::Diagnostic>)>;
+    let mut tf_diags = Vec::new();
+    let mut summary = Summary::default();
+    let (mut all_diags, overall_sev, source) = match opt.input {
+        InputFormat::Validate => {
+            let r = parse_validate_result(&input, opt.lenient)?;
+            let engine = opt.engine.for_validate();
+            check_format_version(&r, engine)?;
+            let source = opt.source(engine);
+            let overall_sev = overall_severity(r.error_count, r.warning_count);
+            summary.reported(&r);
+            tf_diags = r.diagnostics;
+            let (total, no_range) = (tf_diags.len(), count_no_range(&tf_diags));
+            let mut all_diags = enrich_and_convert(
+                &mut tf_diags,
+                &path_converter,
+                &sources,
+                schema.as_ref(),
+                opt.skip_errors,
+                source,
+            )?;
+            validate_codes(&mut all_diags, engine);
+            summary.skipped(no_range, total - no_range - all_diags.len());
+            (all_diags, overall_sev, source)
+        }
+        InputFormat::Terragrunt => {
+            units = terragrunt::split_run_all(&input);
+            let results = parse_run_all(&units, opt)?;
+            let engine = opt.engine.for_validate();
+            for (_, r) in &results {
+                check_format_version(r, engine)?;
+            }
+            let source = opt.source(engine);
+            let overall_sev = overall_severity(
+                results.iter().map(|(_, r)| r.error_count).sum(),
+                results.iter().map(|(_, r)| r.warning_count).sum(),
+            );
+            for (_, r) in &results {
+                summary.reported(r);
+            }
+            unit_diags = results
+                .into_iter()
+                .map(|(unit, r)| (unit.dir.as_deref(), r.diagnostics))
+                .collect();
+            // Each unit is converted separately to note the root module its diagnostics came from
+            let mut all_diags = Vec::new();
+            for (dir, tf_diags) in unit_diags.iter_mut() {
+                let (total, no_range) = (tf_diags.len(), count_no_range(tf_diags));
+                let mut diags = enrich_and_convert(
+                    tf_diags,
+                    &path_converter,
+                    &sources,
+                    schema.as_ref(),
+                    opt.skip_errors,
+                    source,
+                )?;
+                validate_codes(&mut diags, engine);
+                summary.skipped(no_range, total - no_range - diags.len());
+                let root_module = summary::root_dir(&path_converter, dir.unwrap_or("."));
+                for diag in &mut diags {
+                    diag.root_module = Some(root_module.clone());
+                }
+                all_diags.append(&mut diags);
+            }
+            (all_diags, overall_sev, source)
+        }
+        InputFormat::Stream | InputFormat::Test => {
+            let mut test_results = TestResults::default();
+            let mut file_results = Vec::new();
+            let mut detected = None;
+            for line in input.lines() {
+                if let Some(mut message) = parse_stream_line(line, opt.lenient)? {
+                    detected = detected.or_else(|| Engine::detect_stream(&message));
+                    if let InputFormat::Test = opt.input {
+                        tftest::annotate(&mut message);
+                        file_results.extend(test_results.observe(&message));
+                    }
+                    tf_diags.extend(message.diagnostic);
+                }
+            }
+            let engine = opt.engine.resolve(detected);
+            let source = opt.source(engine);
+            summary.test_summary = test_results.summary(engine);
+            let overall_sev = overall_severity(
+                count_severity(&tf_diags, "error") + file_results.len() as u32,
+                count_severity(&tf_diags, "warning"),
+            );
+            let (total, no_range) = (tf_diags.len(), count_no_range(&tf_diags));
+            let mut all_diags = enrich_and_convert(
+                &mut tf_diags,
+                &path_converter,
+                &sources,
+                schema.as_ref(),
+                opt.skip_errors,
+                source,
+            )?;
+            summary.skipped(no_range, total - no_range - all_diags.len());
+            for file_result in file_results {
+                match convert_file_result(
+                    &file_result,
+                    &path_converter,
+                    opt.skip_errors,
+                    source,
+                    engine,
+                )? {
+                    Some(diag) => all_diags.push(diag),
+                    None => summary.skipped(0, 1),
+                }
+            }
+            (all_diags, overall_sev, source)
+        }
+        InputFormat::FmtDiff => {
+            let engine = opt.engine.resolve(None);
+            let source = opt.source(engine);
+            let (all_diags, unconverted) =
+                fmtdiff::convert(&input, &path_converter, opt.skip_errors, source, engine)?;
+            summary.skipped(0, unconverted);
+            let overall_sev = highest_severity(&all_diags);
+            (all_diags, overall_sev, source)
+        }
+        InputFormat::Tfsec => {
+            let source = opt.source(Engine::Auto);
+            let report: tfsec::Report = serde_json::from_str(&input)
+                .map_err(|e| Error::parse_as(e, &input, "tfsec --format json"))?;
+            let findings = report.findings(&opt.severity_map.clone().unwrap_or_default());
+            let total = findings.len();
+            let all_diags = scanner::convert(findings, &path_converter, opt.skip_errors, source)?;
+            summary.skipped(0, total - all_diags.len());
+            let overall_sev = highest_severity(&all_diags);
+            (all_diags, overall_sev, source)
+        }
+        InputFormat::Trivy => {
+            let source = opt.source(Engine::Auto);
+            let report: trivy::Report = serde_json::from_str(&input)
+                .map_err(|e| Error::parse_as(e, &input, "trivy config --format json"))?;
+            let findings = report.findings(&opt.severity_map.clone().unwrap_or_default());
+            let total = findings.len();
+            let all_diags = scanner::convert(findings, &path_converter, opt.skip_errors, source)?;
+            summary.skipped(0, total - all_diags.len());
+            let overall_sev = highest_severity(&all_diags);
+            (all_diags, overall_sev, source)
+        }
+        InputFormat::Checkov => {
+            let source = opt.source(Engine::Auto);
+            let reports = checkov::parse(&input)
+                .map_err(|e| Error::parse_as(e, &input, "checkov -o json"))?;
+            let findings = checkov::findings(
+                reports,
+                &opt.severity_map.clone().unwrap_or_default(),
+                opt.basedir.is_some(),
+            );
+            let total = findings.len();
+            let all_diags = scanner::convert(findings, &path_converter, opt.skip_errors, source)?;
+            summary.skipped(0, total - all_diags.len());
+            let overall_sev = highest_severity(&all_diags);
+            (all_diags, overall_sev, source)
+        }
+    };
+
+    // The summary counts diagnostics by root module, and the HTML report filters by it
+    if opt.summary.is_some() || matches!(opt.format, OutputFormat::Html) {
+        let workdir_root = summary::root_dir(&path_converter, ".");
+        for diag in all_diags.iter_mut().filter(|d| d.root_module.is_none()) {
+            diag.root_module = Some(match opt.input {
+                // Scanners and terraform fmt cover a whole tree of modules
+                InputFormat::FmtDiff
+                | InputFormat::Tfsec
+                | InputFormat::Trivy
+                | InputFormat::Checkov => {
+                    summary::root_on_disk(&diag.location.path, opt.basedir.as_deref())
+                }
+                _ => workdir_root.clone(),
+            });
+        }
+    }
+    let all_diags: Vec<_> = all_diags
+        .into_iter()
+        .filter_map(|diag| attribute_owners(diag, codeowners.as_ref(), opt, &mut summary))
+        .collect();
+    for diag in &all_diags {
+        summary.record(diag);
+    }
+    write_output(
+        &mut stdout.lock(),
+        &opt.format,
+        all_diags,
+        overall_sev,
+        source,
+        opt.fail_on,
+        opt.end_position,
+    )?;
+    write_summary(opt, summary, &mut io::stderr())
+}
+
+fn load_codeowners(opt: &Opt) -> Result<Option<CodeOwners>, Error> {
+    if !opt.codeowners && opt.owner.is_empty() {
+        return Ok(None);
+    }
+    let base = opt.basedir.as_deref().unwrap_or_else(|| Path::new("."));
+    codeowners::load(base).map(Some)
+}
+
+// Notes the owners of a diagnostic's file, or returns None if it should be left out
+// as none of them were given with --owner.
+fn attribute_owners<'a>(
+    mut diag: rd::Diagnostic<'a>,
+    codeowners: Option<&CodeOwners>,
+    opt: &Opt,
+    summary: &mut Summary,
+) -> Option<rd::Diagnostic<'a>> {
+    if let Some(codeowners) = codeowners {
+        codeowners.attribute(&mut diag);
+        if !opt.owner.is_empty() && !codeowners::owned_by(&diag, &opt.owner) {
+            summary.skipped_owner();
+            return None;
+        }
+    }
+    Some(diag)
+}
+
+fn write_summary(opt: &Opt, mut summary: Summary, err: &mut dyn Write) -> Result<(), Error> {
+    // The diagnostics only cover what failed, so the outcome of the whole test run is
+    // shown too, on stderr as stdout has the diagnostics
+    match &summary.test_summary {
+        Some(test_summary) if !opt.no_test_summary => {
+            writeln!(err, "{}", test_summary).map_err(Error::WriteOutput)?
+        }
+        _ => {}
+    }
+    match &opt.summary {
+        Some(path) => {
+            summary.cross_check();
+            summary.write(path)
+        }
+        None => Ok(()),
+    }
+}
+
+fn enrich_and_convert<'a>(
+    tf_diags: &'a mut [tf::Diagnostic],
+    path_converter: &dyn Fn(&str) -> Result<String, Error>,
+    sources: &SourceFiles,
+    schema: Option<&ProviderSchemas>,
+    skip_errors: bool,
+    source: rd::Source<'a>,
+) -> Result<Vec<rd::Diagnostic<'a>>, Error> {
+    for tf_diag in tf_diags.iter_mut() {
+        enrich::enrich(tf_diag, sources);
+    }
+    convert(
+        tf_diags,
+        path_converter,
+        sources,
+        schema,
+        skip_errors,
+        source,
+    )
+}
+
+// Terraform has no rule codes, so validate diagnostics are given one from their
+// summary, which is the same wherever the problem is, linking to validate's docs.
+fn validate_codes(all_diags: &mut [rd::Diagnostic], engine: Engine) {
+    let docs_url = engine.docs_url("validate");
+    for diag in all_diags.iter_mut().filter(|d| d.code.is_none()) {
+        if let Some(summary) = &diag.summary {
+            diag.code = Some(rd::Code {
+                value: Cow::Owned(slug(summary)),
+                url: Some(Cow::Owned(docs_url.clone())),
+            });
+        }
+    }
+}
+
+// Lower case words joined by "-", e.g. "invalid-quoted-type-constraints", for an id.
+fn slug(name: &str) -> String {
+    let mut id = String::with_capacity(name.len());
+    for word in name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if !id.is_empty() {
+            id.push('-');
         }
+        id.push_str(&word.to_lowercase());
+    }
+    id
+}
+
+fn overall_severity(error_count: u32, warning_count: u32) -> rd::Severity {
+    if error_count > 0 {
+        rd::Severity::Error
+    } else if warning_count > 0 {
+        rd::Severity::Warning
+    } else {
+        rd::Severity::Info
+    }
+}
+
+fn highest_severity(all_diags: &[rd::Diagnostic]) -> rd::Severity {
+    let count = |severity| {
+        all_diags
+            .iter()
+            .filter(|d| d.severity == Some(severity))
+            .count() as u32
+    };
+    overall_severity(count(rd::Severity::Error), count(rd::Severity::Warning))
+}
+
+fn write_output(
+    out: &mut dyn Write,
+    format: &OutputFormat,
+    all_diags: Vec<rd::Diagnostic>,
+    overall_sev: rd::Severity,
+    source: rd::Source,
+    fail_on: FailOn,
+    end_position: bool,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::RdJson => serde_json::to_writer(
+            out,
+            &rd::DiagnosticResult {
+                diagnostics: all_diags.into_iter().map(codeowners::name_owners).collect(),
+                severity: Some(overall_sev),
+                source: Some(source),
+            },
+        )
+        .map_err(|e| Error::WriteOutput(e.into())),
         OutputFormat::RdJsonL => {
-            let mut stdout = io::stdout();
             for diag in all_diags {
-                serde_json::to_writer(&stdout, &diag)?;
-                stdout.write_all(b"\n")?;
+                write_rdjsonl(out, &codeowners::name_owners(diag))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Markdown => {
+            markdown::write(out, &all_diags, overall_sev, source).map_err(Error::WriteOutput)
+        }
+        OutputFormat::Html => {
+            html::write(out, &all_diags, overall_sev, source).map_err(Error::WriteOutput)
+        }
+        OutputFormat::Bitbucket => {
+            bitbucket::write(out, &all_diags, overall_sev, source, bitbucket::Api::Cloud)
+        }
+        OutputFormat::BitbucketServer => {
+            bitbucket::write(out, &all_diags, overall_sev, source, bitbucket::Api::Server)
+        }
+        OutputFormat::Azure => {
+            azure::write(out, &all_diags, overall_sev, fail_on).map_err(Error::WriteOutput)
+        }
+        OutputFormat::SonarQube => sonarqube::write(out, &all_diags, source),
+        OutputFormat::Plain => {
+            plain::write(out, &all_diags, end_position).map_err(Error::WriteOutput)
+        }
+        OutputFormat::RdPb => {
+            protobuf::write_result(out, &all_diags, overall_sev, source).map_err(Error::WriteOutput)
+        }
+        OutputFormat::RdPbStream => {
+            protobuf::write_diagnostics(out, &all_diags).map_err(Error::WriteOutput)
+        }
+    }
+}
+
+fn write_rdjsonl(out: &mut dyn Write, diag: &rd::Diagnostic) -> Result<(), Error> {
+    serde_json::to_writer(&mut *out, diag).map_err(|e| Error::WriteOutput(e.into()))?;
+    out.write_all(b"\n").map_err(Error::WriteOutput)?;
+    // Flush each line so annotations show up while a long-running terraform command is still going
+    out.flush().map_err(Error::WriteOutput)
+}
+
+// Reads a stream of terraform JSON messages one line at a time, writing each
+// converted diagnostic as soon as it's read.
+fn stream_rdjsonl(
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    opt: &Opt,
+    path_converter: &PathConverter,
+    sources: &SourceFiles,
+    schema: Option<&ProviderSchemas>,
+    codeowners: Option<&CodeOwners>,
+) -> Result<Summary, Error> {
+    let mut engine = opt.engine.resolve(None);
+    let mut test_results = TestResults::default();
+    let mut summary = Summary::default();
+    let root_module = summary::root_dir(path_converter, ".");
+    let mut line = String::with_capacity(256);
+    loop {
+        line.clear();
+        if input.read_line(&mut line).map_err(Error::ReadInput)? == 0 {
+            summary.test_summary = test_results.summary(engine);
+            return Ok(summary);
+        }
+        let mut message = match parse_stream_line(&line, opt.lenient)? {
+            Some(message) => message,
+            None => continue,
+        };
+        if opt.engine == Engine::Auto {
+            engine = Engine::detect_stream(&message).unwrap_or(engine);
+        }
+        let source = opt.source(engine);
+        let mut file_result = None;
+        if let InputFormat::Test = opt.input {
+            tftest::annotate(&mut message);
+            file_result = test_results.observe(&message);
+        }
+        if let Some(mut tf_diag) = message.diagnostic {
+            enrich::enrich(&mut tf_diag, sources);
+            match convert_or_skip(
+                &tf_diag,
+                path_converter,
+                sources,
+                schema,
+                opt.skip_errors,
+                source,
+            )? {
+                Some(mut diag) => {
+                    diag.root_module = Some(root_module.clone());
+                    if let Some(diag) = attribute_owners(diag, codeowners, opt, &mut summary) {
+                        summary.record(&diag);
+                        write_rdjsonl(out, &codeowners::name_owners(diag))?;
+                    }
+                }
+                None if tf_diag.range.is_none() => summary.skipped(1, 0),
+                None => summary.skipped(0, 1),
+            }
+        }
+        if let Some(file_result) = file_result {
+            match convert_file_result(
+                &file_result,
+                path_converter,
+                opt.skip_errors,
+                source,
+                engine,
+            )? {
+                Some(mut diag) => {
+                    diag.root_module = Some(root_module.clone());
+                    if let Some(diag) = attribute_owners(diag, codeowners, opt, &mut summary) {
+                        summary.record(&diag);
+                        write_rdjsonl(out, &codeowners::name_owners(diag))?;
+                    }
+                }
+                None => summary.skipped(0, 1),
             }
         }
     }
-    Ok(())
 }
 
-type PathConverter = Box<dyn Fn(&str) -> Result<String, io::Error>>;
+// Converts the summary of a failed test file, or returns None if it should be left out of the output.
+fn convert_file_result<'a>(
+    file_result: &tftest::FileResult,
+    path_converter: &dyn Fn(&str) -> Result<String, Error>,
+    skip_errors: bool,
+    source: rd::Source<'a>,
+    engine: Engine,
+) -> Result<Option<rd::Diagnostic<'a>>, Error> {
+    match file_result.to_diagnostic(path_converter, source, engine) {
+        Err(e) if skip_errors => {
+            warn!(
+                "The result of test file {} could not be converted and will be ignored: {}",
+                file_result.path, e
+            );
+            Ok(None)
+        }
+        result => result.map(Some),
+    }
+}
+
+// Parses one line of a terraform JSON message stream.
+fn parse_stream_line(line: &str, lenient: bool) -> Result<Option<tf::StreamMessage<'_>>, Error> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    match serde_json::from_str::<tf::StreamMessage>(line) {
+        Ok(message) => Ok(Some(message)),
+        Err(e) if lenient => {
+            warn!(
+                "Ignoring a line of input that is not a terraform JSON message: {}",
+                e
+            );
+            Ok(None)
+        }
+        Err(e) => Err(Error::parse(e, line)),
+    }
+}
+
+fn count_severity(tf_diags: &[tf::Diagnostic], severity: &str) -> u32 {
+    tf_diags.iter().filter(|d| d.severity == severity).count() as u32
+}
+
+fn count_no_range(tf_diags: &[tf::Diagnostic]) -> usize {
+    tf_diags.iter().filter(|d| d.range.is_none()).count()
+}
+
+type PathConverter = Box<dyn Fn(&str) -> Result<String, Error>>;
 
-fn make_path_converter(opt: &Opt) -> Result<PathConverter, io::Error> {
+fn make_path_converter(opt: &Opt) -> Result<PathConverter, Error> {
     Ok(if let Some(workdir) = &opt.workdir {
         // If we have a workdir set we can convert relative paths in Terraform output to absolute paths
-        let abs_work = workdir.absolutize()?.to_path_buf();
-        let absolutize_path =
-            path_fn(move |filename| Path::new(filename).absolutize_from(&abs_work));
+        let abs_work = absolutize_dir(workdir)?;
+        let sources = terragrunt::Sources::default();
+        let absolutize_path = path_fn(move |filename| {
+            let absolute = Path::new(filename)
+                .absolutize_from(&abs_work)
+                .map_err(|e| Error::PathConversion {
+                    path: filename.to_owned(),
+                    reason: e.to_string(),
+                })?;
+            // Point at the original of any file in the Terragrunt cache
+            Ok(terragrunt::uncache(&absolute, &sources).map_or(absolute, Cow::Owned))
+        });
 
         if let Some(basedir) = &opt.basedir {
             // If we also have a basedir we can further convert the absolute paths to be relative to the root of the project or repository
-            let abs_base = basedir.absolutize()?.to_path_buf();
+            let abs_base = absolutize_dir(basedir)?;
             let relativize_path = move |filename: &str| {
                 let absolute = absolutize_path(filename)?;
                 diff_paths(absolute, &abs_base)
-                    .ok_or_else(|| {
-                        io::Error::new(
-                            io::ErrorKind::Other,
-                            format!(
-                                "Can't convert '{}' into a path relative to '{}'",
-                                filename,
-                                abs_base.to_string_lossy()
-                            ),
-                        )
+                    .ok_or_else(|| Error::PathConversion {
+                        path: filename.to_owned(),
+                        reason: format!(
+                            "it can't be made relative to '{}'",
+                            abs_base.to_string_lossy()
+                        ),
                     })
                     .and_then(path_to_string)
             };
@@ -230,13 +990,35 @@ fn make_path_converter(opt: &Opt) -> Result<PathConverter, io::Error> {
             })
         }
     } else {
-        // If we have no workdir we can only pass the paths straight through
-        Box::new(|filename| Ok(filename.to_owned()))
+        // If we have no workdir we can only pass the paths straight through,
+        // apart from those in the Terragrunt cache
+        let sources = terragrunt::Sources::default();
+        Box::new(
+            move |filename| match terragrunt::uncache(Path::new(filename), &sources) {
+                Some(original) => path_to_string(original),
+                None => Ok(filename.to_owned()),
+            },
+        )
     })
 }
 
+fn absolutize_dir(dir: &Path) -> Result<PathBuf, Error> {
+    dir.absolutize()
+        .map(|p| p.to_path_buf())
+        .map_err(|e| Error::PathConversion {
+            path: dir.to_string_lossy().into_owned(),
+            reason: e.to_string(),
+        })
+}
+
 #[cfg(test)]
+#[allow(clippy::iter_next_slice, clippy::needless_borrow)]
 mod tests {
+    use std::cell::Cell;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::fs;
+    use std::rc::Rc;
+
     use jsonschema::{Draft, JSONSchema};
     use serde_json::json;
 
@@ -246,8 +1028,28 @@ mod tests {
     static TF_NO_RANGE: &str = include_str!("../testdata/no_range.json");
     static TF_MODS_IN_PARENT: &str = include_str!("../testdata/modules_parent_dir.json");
     static TF_QUOTING: &str = include_str!("../testdata/quoting.json");
+    static TF_MULTIBYTE: &str = include_str!("../testdata/multibyte.json");
+    static TF_MISSING_END: &str = include_str!("../testdata/missing_end.json");
+    static TF_FMT_DIFF: &str = include_str!("../testdata/fmt_diff.txt");
+    static TFSEC: &str = include_str!("../testdata/tfsec.json");
+    static TRIVY: &str = include_str!("../testdata/trivy.json");
+    static CHECKOV: &str = include_str!("../testdata/checkov.json");
+    static TF_TEST_OUTPUT: &str = include_str!("../testdata/test_output.jsonl");
+    static TF_PLAN_STREAM: &str = include_str!("../testdata/plan_stream.jsonl");
+    static TERRAGRUNT_RUN_ALL: &str = include_str!("../testdata/terragrunt_run_all.txt");
+    static TF_SUGGESTIONS: &str = include_str!("../testdata/suggestions.json");
+    static TF_TYPOS: &str = include_str!("../testdata/typos.json");
+    static TOFU_PLAN_STREAM: &str = include_str!("../testdata/tofu_plan_stream.jsonl");
+    static TF_WRAPPER_OUTPUT: &str = include_str!("../testdata/wrapper_output.txt");
+    static TF_LOG_LINES: &str = include_str!("../testdata/tf_log.txt");
+    static TF_HUMAN_READABLE: &str = include_str!("../testdata/human_readable.txt");
+    static CODEOWNERS: &str = include_str!("../testdata/CODEOWNERS");
 
-    fn passthru_path(s: &str) -> Result<String, io::Error> {
+    fn test_source(name: &str) -> rd::Source<'_> {
+        rd::Source { name, url: None }
+    }
+
+    fn passthru_path(s: &str) -> Result<String, Error> {
         Ok(s.to_owned())
     }
 
@@ -255,8 +1057,15 @@ mod tests {
     fn test_no_range() {
         let result: tf::ValidateResult =
             serde_json::from_str(TF_NO_RANGE).expect("Test data should be parsed");
-        let all_diags = convert(&result, &Box::new(passthru_path), false, "test_no_range")
-            .expect("Test data should be converted");
+        let all_diags = convert(
+            &result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            test_source("test_no_range"),
+        )
+        .expect("Test data should be converted");
         assert_eq!(
             all_diags.len(),
             1,
@@ -285,8 +1094,15 @@ mod tests {
     fn test_quoting_errors() {
         let result: tf::ValidateResult =
             serde_json::from_str(TF_QUOTING).expect("Test data should be parsed");
-        let all_diags = convert(&result, &Box::new(passthru_path), false, "test_quoting")
-            .expect("Test data should be converted");
+        let all_diags = convert(
+            &result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            test_source("test_quoting"),
+        )
+        .expect("Test data should be converted");
         assert_eq!(all_diags.len(), 2, "Two diagnostics should be included");
         assert_eq!(
             serde_json::to_value(all_diags).expect("Converted data should be serialized"),
@@ -331,19 +1147,18 @@ mod tests {
             let tf_in: tf::ValidateResult =
                 serde_json::from_str(input).expect("Test data can be parsed");
             let all_diags = convert(
-                &tf_in,
+                &tf_in.diagnostics,
                 &Box::new(passthru_path),
+                &SourceFiles::new(None),
+                None,
                 false,
-                "schema_validate_output",
+                test_source("schema_validate_output"),
             )
             .expect("Diagnostics can be converted");
             let rd_diag = rd::DiagnosticResult {
                 diagnostics: all_diags,
                 severity: Some(rd::Severity::Error),
-                source: Some(Source {
-                    name: "schema_validate_output",
-                    url: None,
-                }),
+                source: Some(test_source("schema_validate_output")),
             };
             compiled_schema
                 .validate(
@@ -355,28 +1170,1833 @@ mod tests {
     }
 
     #[test]
+    #[rustfmt::skip]
     fn test_make_path_converter_workdir() {
         let path_converter = make_path_converter(&Opt {
             basedir: None,
             workdir: Some("/abs/example/dir".into()),
             skip_errors: false,
+            input: InputFormat::Validate,
+            lenient: false,
+            engine: Engine::Auto,
+            severity_map: None,
             format: OutputFormat::RdJsonL,
-            source: "test".to_string(),
+            source: Some("test".to_string()),
+            no_test_summary: false,
+            summary: None,
+            source_url: None,
+            fail_on: FailOn::Error,
+            codeowners: false,
+            owner: Vec::new(),
+            end_position: false,
+            command: None,
+            provider_schema: None,
         }).expect("can create path_converter");
         assert_eq!(path_converter(&"../somefile.tf").unwrap(), "/abs/example/somefile.tf");
         assert_eq!(path_converter(&"sub/dir/somefile.tf").unwrap(), "/abs/example/dir/sub/dir/somefile.tf");
     }
 
     #[test]
+    #[rustfmt::skip]
     fn test_make_path_converter_relativize() {
         let path_converter = make_path_converter(&Opt {
             basedir: Some("/abs/example".into()),
             workdir: Some("/abs/example/dir".into()),
             skip_errors: false,
+            input: InputFormat::Validate,
+            lenient: false,
+            engine: Engine::Auto,
+            severity_map: None,
             format: OutputFormat::RdJsonL,
-            source: "test".to_string(),
+            source: Some("test".to_string()),
+            no_test_summary: false,
+            summary: None,
+            source_url: None,
+            fail_on: FailOn::Error,
+            codeowners: false,
+            owner: Vec::new(),
+            end_position: false,
+            command: None,
+            provider_schema: None,
         }).expect("can create path_converter");
         assert_eq!(path_converter(&"../somefile.tf").unwrap(), "somefile.tf");
         assert_eq!(path_converter(&"sub/dir/somefile.tf").unwrap(), "dir/sub/dir/somefile.tf");
     }
+
+    #[test]
+    fn test_terragrunt_cache_paths() {
+        let cache = "testdata/terragrunt/live/prod/vpc/.terragrunt-cache/a1b2c3/d4e5f6";
+        let sources = terragrunt::Sources::default();
+        assert_eq!(
+            terragrunt::uncache(Path::new(&format!("{}/vpc/main.tf", cache)), &sources),
+            Some(PathBuf::from("testdata/terragrunt/modules/vpc/main.tf"))
+        );
+        // Files of the unit itself are copied over the module
+        assert_eq!(
+            terragrunt::uncache(Path::new(&format!("{}/vpc/provider.tf", cache)), &sources),
+            Some(PathBuf::from(
+                "testdata/terragrunt/live/prod/vpc/provider.tf"
+            ))
+        );
+        let remote = "testdata/terragrunt/live/prod/app/.terragrunt-cache/a1b2c3/d4e5f6";
+        assert_eq!(
+            terragrunt::uncache(Path::new(&format!("{}/app/main.tf", remote)), &sources),
+            None
+        );
+        assert_eq!(
+            terragrunt::uncache(Path::new(&format!("{}/app/backend.tf", remote)), &sources),
+            Some(PathBuf::from(
+                "testdata/terragrunt/live/prod/app/backend.tf"
+            ))
+        );
+        // Each unit's terragrunt.hcl is only read once
+        assert_eq!(sources.borrow().len(), 2);
+
+        let path_converter = make_path_converter(&Opt::from_iter(&[
+            "tfv2rd",
+            "--workdir",
+            &format!("{}/vpc", cache),
+            "--basedir",
+            "testdata/terragrunt",
+        ]))
+        .expect("can create path_converter");
+        assert_eq!(path_converter("main.tf").unwrap(), "modules/vpc/main.tf");
+        assert_eq!(
+            path_converter("../shared/outputs.tf").unwrap(),
+            "modules/shared/outputs.tf"
+        );
+    }
+
+    #[test]
+    fn test_terragrunt_run_all() {
+        let opt = Opt::from_iter(&[
+            "tfv2rd",
+            "--input",
+            "terragrunt",
+            "--workdir",
+            "testdata/terragrunt",
+            "--basedir",
+            "testdata/terragrunt",
+        ]);
+        let units = terragrunt::split_run_all(TERRAGRUNT_RUN_ALL);
+        let dirs = units.iter().map(|u| u.dir.as_deref()).collect::<Vec<_>>();
+        assert_eq!(dirs, vec![Some("live/prod/vpc"), Some("live/prod/app")]);
+        let results = parse_run_all(&units, &opt).expect("Test data should be parsed");
+        let mut tf_diags: Vec<tf::Diagnostic> = results
+            .into_iter()
+            .flat_map(|(_, r)| r.diagnostics)
+            .collect();
+        let all_diags = enrich_and_convert(
+            &mut tf_diags,
+            &make_path_converter(&opt).expect("can create path_converter"),
+            &SourceFiles::new(opt.workdir.clone()),
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        let paths = all_diags
+            .iter()
+            .map(|d| d.location.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec!["modules/vpc/main.tf", "live/prod/app/backend.tf"]
+        );
+    }
+
+    #[test]
+    fn test_check_format_version() {
+        for (engine, version, supported) in [
+            (Engine::Terraform, "0.1", true),
+            (Engine::Terraform, "1.0", true),
+            (Engine::Terraform, "2.0", false),
+            (Engine::Terraform, "1", true),
+            (Engine::Tofu, "0.1", false),
+            (Engine::Tofu, "1.0", true),
+        ] {
+            let input = format!(
+                r#"{{"format_version": "{}", "valid": true, "error_count": 0, "warning_count": 0, "diagnostics": []}}"#,
+                version
+            );
+            let result: tf::ValidateResult =
+                serde_json::from_str(&input).expect("Test data should be parsed");
+            assert_eq!(
+                check_format_version(&result, engine).is_ok(),
+                supported,
+                "{:?} format_version {}",
+                engine,
+                version
+            );
+        }
+
+        let unsupported = |engine, version| {
+            let input = format!(
+                r#"{{"format_version": "{}", "valid": true, "error_count": 0, "warning_count": 0, "diagnostics": []}}"#,
+                version
+            );
+            let result: tf::ValidateResult =
+                serde_json::from_str(&input).expect("Test data should be parsed");
+            check_format_version(&result, engine)
+                .expect_err("The format_version shouldn't be supported")
+                .to_string()
+        };
+        assert!(unsupported(Engine::Tofu, "0.1")
+            .ends_with("tfv2rd understands tofu validate -json format versions 1.x"));
+        assert!(unsupported(Engine::Terraform, "2.0")
+            .ends_with("tfv2rd understands terraform validate -json format versions 0.x and 1.x"));
+    }
+
+    #[test]
+    fn test_parse_error_explains_input() {
+        let input = "Success! The configuration is valid.\n";
+        let err = serde_json::from_str::<tf::ValidateResult>(input)
+            .map_err(|e| Error::parse(e, input))
+            .expect_err("Plain text should not be parsed");
+        assert_eq!(err.exit_code(), 65);
+        let message = err.to_string();
+        assert!(message.contains("terraform validate -json"), "{}", message);
+        assert!(message.contains("near: Success!"), "{}", message);
+
+        let input = r#"{"resource_changes": []}"#;
+        let err = serde_json::from_str::<tf::ValidateResult>(input)
+            .map_err(|e| Error::parse(e, input))
+            .expect_err("Other JSON should not be parsed");
+        assert!(err
+            .to_string()
+            .contains("but not `terraform validate -json` output"));
+    }
+
+    #[test]
+    fn test_diagnose_input_problems() {
+        for (input, problem) in [
+            (TF_WRAPPER_OUTPUT, input::InputProblem::WrapperOutput),
+            (TF_LOG_LINES, input::InputProblem::LogLines),
+            (TF_HUMAN_READABLE, input::InputProblem::HumanReadable),
+        ] {
+            match parse_validate_result(input, false) {
+                Err(Error::UnexpectedInput(p)) => assert_eq!(p, problem),
+                other => panic!("Expected {:?} but got {:?}", problem, other),
+            }
+        }
+        assert_eq!(
+            input::diagnose(&format!("Running validate...\n{}", TF_QUOTING)),
+            Some(input::InputProblem::SurroundingText)
+        );
+        assert_eq!(input::diagnose("{\"not\": \"terraform\"}"), None);
+    }
+
+    #[test]
+    fn test_lenient_skips_noise() {
+        let result = parse_validate_result(TF_WRAPPER_OUTPUT, true)
+            .expect("Wrapper output should be parsed");
+        assert_eq!(result.error_count, 2);
+        assert_eq!(result.diagnostics.len(), 2);
+
+        let result =
+            parse_validate_result(TF_LOG_LINES, true).expect("Log output should be parsed");
+        assert_eq!(result.diagnostics.len(), 2);
+
+        // Objects that don't look like validate output are skipped, including their nested objects
+        let input = format!(
+            "{{\"other\": {{\"format_version\": \"1.0\"}}}} {{oops\n{}",
+            TF_QUOTING
+        );
+        let result = parse_validate_result(&input, true).expect("Noisy input should be parsed");
+        assert_eq!(result.diagnostics.len(), 2);
+
+        assert!(parse_validate_result(TF_HUMAN_READABLE, true).is_err());
+    }
+
+    #[test]
+    fn test_stream_rdjsonl() {
+        let opt = Opt::from_iter(&["tfv2rd", "--input", "stream", "--source", "test_stream"]);
+        let mut output = Vec::new();
+        stream_rdjsonl(
+            &mut TF_PLAN_STREAM.as_bytes(),
+            &mut output,
+            &opt,
+            &make_path_converter(&opt).expect("can create path_converter"),
+            &SourceFiles::new(None),
+            None,
+            None,
+        )
+        .expect("Test data should be converted");
+        let lines = std::str::from_utf8(&output)
+            .expect("Output should be UTF-8")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("Each line should be JSON"))
+            .collect::<Vec<serde_json::Value>>();
+        assert_eq!(lines.len(), 2, "Only diagnostics with a range are output");
+        assert_eq!(lines[0]["severity"], "WARNING");
+        assert_eq!(lines[0]["location"]["path"], "main.tf");
+        assert_eq!(lines[1]["message"], "Unsupported attribute");
+        assert_eq!(lines[1]["location"]["path"], "modules/lb/outputs.tf");
+    }
+
+    #[test]
+    fn test_detect_opentofu() {
+        // validate output doesn't say which tool produced it
+        assert_eq!(Engine::Auto.for_validate(), Engine::Terraform);
+        assert_eq!(Engine::Tofu.for_validate(), Engine::Tofu);
+
+        let opt = Opt::from_iter(&["tfv2rd", "--input", "stream"]);
+        let mut output = Vec::new();
+        stream_rdjsonl(
+            &mut TOFU_PLAN_STREAM.as_bytes(),
+            &mut output,
+            &opt,
+            &make_path_converter(&opt).expect("can create path_converter"),
+            &SourceFiles::new(None),
+            None,
+            None,
+        )
+        .expect("Test data should be converted");
+        let line: serde_json::Value =
+            serde_json::from_slice(&output).expect("Output should be JSON");
+        assert_eq!(line["source"]["name"], "tofu");
+
+        // An explicit engine overrides detection
+        let opt = Opt::from_iter(&["tfv2rd", "--engine", "terraform", "--input", "stream"]);
+        assert_eq!(
+            opt.source_name(opt.engine.resolve(Some(Engine::Tofu))),
+            "terraform"
+        );
+        let opt = Opt::from_iter(&["tfv2rd", "--engine", "tofu"]);
+        assert_eq!(opt.source_name(opt.engine.for_validate()), "tofu validate");
+        let opt = Opt::from_iter(&["tfv2rd", "--engine", "opentofu", "--input", "fmt-diff"]);
+        assert_eq!(opt.source_name(opt.engine.resolve(None)), "tofu fmt");
+        assert_eq!(
+            Engine::Tofu.docs_url("fmt"),
+            "https://opentofu.org/docs/cli/commands/fmt/"
+        );
+    }
+
+    #[test]
+    fn test_validate_codes() {
+        let tf_result =
+            parse_validate_result(TF_QUOTING, false).expect("Test data should be parsed");
+        let mut all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            test_source("terraform validate"),
+        )
+        .expect("Test data should be converted");
+        validate_codes(&mut all_diags, Engine::Terraform);
+        assert_eq!(
+            serde_json::to_value(&all_diags[0].code).expect("Code should be serialized"),
+            json!({
+                "value": "invalid-quoted-type-constraints",
+                "url": "https://developer.hashicorp.com/terraform/cli/commands/validate"
+            })
+        );
+        // The same problem elsewhere has the same code
+        assert_eq!(
+            all_diags[1].code.as_ref().map(|c| &c.value),
+            all_diags[0].code.as_ref().map(|c| &c.value)
+        );
+    }
+
+    #[test]
+    fn test_source_url() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            ci::job_url_from(env(&[
+                ("GITHUB_SERVER_URL", "https://github.com"),
+                ("GITHUB_REPOSITORY", "sigmaris/tfv2rd"),
+                ("GITHUB_RUN_ID", "1234567890"),
+            ])),
+            Some("https://github.com/sigmaris/tfv2rd/actions/runs/1234567890".to_owned())
+        );
+        assert_eq!(
+            ci::job_url_from(env(&[(
+                "CI_JOB_URL",
+                "https://gitlab.example.com/group/project/-/jobs/42"
+            )])),
+            Some("https://gitlab.example.com/group/project/-/jobs/42".to_owned())
+        );
+        assert_eq!(ci::job_url_from(env(&[("GITHUB_RUN_ID", "1")])), None);
+
+        let opt = Opt::from_iter(&[
+            "tfv2rd",
+            "--format",
+            "rdjson",
+            "--source-url",
+            "https://ci.example.com/job/7",
+        ]);
+        let tf_result =
+            parse_validate_result(TF_QUOTING, false).expect("Test data should be parsed");
+        let source = opt.source(Engine::Terraform);
+        let all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            source,
+        )
+        .expect("Test data should be converted");
+        let mut output = Vec::new();
+        write_output(
+            &mut output,
+            &opt.format,
+            all_diags,
+            rd::Severity::Error,
+            source,
+            FailOn::Error,
+            false,
+        )
+        .expect("Output should be written");
+        let result: serde_json::Value =
+            serde_json::from_slice(&output).expect("Output should be JSON");
+        let expected = json!({"name": "terraform validate", "url": "https://ci.example.com/job/7"});
+        assert_eq!(result["source"], expected);
+        assert_eq!(result["diagnostics"][0]["source"], expected);
+    }
+
+    #[test]
+    fn test_summary() {
+        let tf_result =
+            parse_validate_result(TF_NO_RANGE, false).expect("Test data should be parsed");
+        let mut summary = Summary::default();
+        summary.reported(&tf_result);
+        let all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        summary.skipped(count_no_range(&tf_result.diagnostics), 0);
+        for diag in &all_diags {
+            summary.record(diag);
+        }
+        summary.cross_check();
+        assert_eq!(
+            serde_json::to_value(&summary).expect("Summary should be serialized"),
+            json!({
+                "emitted": 1,
+                "by_severity": {"ERROR": 1},
+                "by_rule": {},
+                "by_file": {"variables.tf": 1},
+                "by_root_module": {".": 1},
+                "skipped_no_range": 1,
+                "skipped_errors": 0,
+                "reported": {"valid": false, "error_count": 2, "warning_count": 0},
+                "discrepancies": [
+                    "terraform reported 2 error(s) but 1 were output",
+                    "1 diagnostic(s) had no source file location and 0 couldn't be converted"
+                ]
+            })
+        );
+
+        let opt = Opt::from_iter(&["tfv2rd", "--input", "stream"]);
+        let summary = stream_rdjsonl(
+            &mut TF_PLAN_STREAM.as_bytes(),
+            &mut Vec::new(),
+            &opt,
+            &make_path_converter(&opt).expect("can create path_converter"),
+            &SourceFiles::new(None),
+            None,
+            None,
+        )
+        .expect("Test data should be converted");
+        assert_eq!(summary.emitted, 2);
+        assert_eq!(summary.skipped_no_range, 1);
+        assert_eq!(summary.by_root_module.get("."), Some(&2));
+        assert!(summary.reported.is_none());
+
+        // Counted under the root module terraform ran in, not the child module of each file
+        let opt = Opt::from_iter(&[
+            "tfv2rd",
+            "--input",
+            "stream",
+            "--workdir",
+            "/abs/example/stacks/web",
+            "--basedir",
+            "/abs/example",
+        ]);
+        let summary = stream_rdjsonl(
+            &mut TF_PLAN_STREAM.as_bytes(),
+            &mut Vec::new(),
+            &opt,
+            &make_path_converter(&opt).expect("can create path_converter"),
+            &SourceFiles::new(None),
+            None,
+            None,
+        )
+        .expect("Test data should be converted");
+        assert_eq!(
+            summary.by_root_module.into_iter().collect::<Vec<_>>(),
+            [("stacks/web".to_owned(), 2)]
+        );
+        // Without an initialised module above it, a scanned file's root module is its directory
+        assert_eq!(
+            summary::root_on_disk(
+                "live/prod/app/backend.tf",
+                Some(Path::new("testdata/terragrunt"))
+            ),
+            "live/prod/app"
+        );
+        assert_eq!(summary::root_on_disk("main.tf", None), ".");
+    }
+
+    #[test]
+    fn test_markdown_output() {
+        let tf_result =
+            parse_validate_result(TF_NO_RANGE, false).expect("Test data should be parsed");
+        let all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            test_source("terraform validate"),
+        )
+        .expect("Test data should be converted");
+        let mut output = Vec::new();
+        write_output(
+            &mut output,
+            &OutputFormat::Markdown,
+            all_diags,
+            rd::Severity::Error,
+            test_source("terraform validate"),
+            FailOn::Error,
+            false,
+        )
+        .expect("Output should be written");
+        assert_eq!(
+            String::from_utf8(output).expect("Output should be UTF-8"),
+            r#"## ❌ terraform validate
+
+| Severity | Count |
+| --- | ---: |
+| ❌ Error | 1 |
+| ⚠️ Warning | 0 |
+| ℹ️ Info | 0 |
+
+### `variables.tf`
+
+<details>
+<summary>❌ <b>Error</b> line 8: Invalid quoted type constraints</summary>
+
+Terraform 0.11 and earlier required type constraints to be given in quotes, but that form is now deprecated and will be removed in a future version of Terraform. Remove the quotes around "string".
+
+```hcl
+  type        = "string"
+```
+
+</details>
+"#
+        );
+
+        let mut output = Vec::new();
+        write_output(
+            &mut output,
+            &OutputFormat::Markdown,
+            Vec::new(),
+            rd::Severity::Info,
+            test_source("terraform validate"),
+            FailOn::Error,
+            false,
+        )
+        .expect("Output should be written");
+        assert_eq!(
+            String::from_utf8(output).expect("Output should be UTF-8"),
+            "## ✅ terraform validate\n\nNo problems found.\n"
+        );
+    }
+
+    #[test]
+    fn test_html_output() {
+        let tf_result =
+            parse_validate_result(TF_NO_RANGE, false).expect("Test data should be parsed");
+        let mut all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            test_source("terraform validate"),
+        )
+        .expect("Test data should be converted");
+        all_diags[0].root_module = Some("live/prod".to_owned());
+        let mut output = Vec::new();
+        write_output(
+            &mut output,
+            &OutputFormat::Html,
+            all_diags,
+            rd::Severity::Error,
+            test_source("terraform validate"),
+            FailOn::Error,
+            false,
+        )
+        .expect("Output should be written");
+        let output = String::from_utf8(output).expect("Output should be UTF-8");
+        assert!(output.starts_with("<!DOCTYPE html>"));
+        assert!(output.contains(
+            r#"<tr class="ERROR" data-severity="ERROR" data-rule="" data-module="live/prod" data-file="variables.tf">"#
+        ));
+        assert!(output.contains(
+            r#"<select id="module"><option value="">All</option><option>live/prod</option></select>"#
+        ));
+        assert!(output.contains(
+            r#"<select id="severity"><option value="">All</option><option>ERROR</option></select>"#
+        ));
+        assert!(output.contains("Remove the quotes around &quot;string&quot;."));
+        assert!(output.contains(
+            r#"<pre><code>  type        = <span class="str">&quot;string&quot;</span></code></pre>"#
+        ));
+        assert!(output.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_lsp_publishes_diagnostics_and_fixes() {
+        let opt = Opt::from_iter(&["tfv2rd", "--engine", "terraform", "lsp"]);
+        assert!(matches!(opt.command, Some(Command::Lsp)));
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/suggestions");
+        let uri = lsp::file_uri(&root.join("main.tf"));
+        let mut input = Vec::new();
+        for message in [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "textDocument/didSave", "params": {"textDocument": {"uri": uri}}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/codeAction", "params": {
+                "textDocument": {"uri": uri},
+                "range": {"start": {"line": 1, "character": 9}, "end": {"line": 1, "character": 9}},
+                "context": {"diagnostics": []},
+            }}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}),
+            json!({"jsonrpc": "2.0", "method": "exit"}),
+        ] {
+            let body = message.to_string();
+            input.extend(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).bytes());
+        }
+        let mut output = Vec::new();
+        lsp::Server::new(&opt, None, Box::new(|_| Ok(TF_SUGGESTIONS.to_owned())))
+            .run(&mut input.as_slice(), &mut output)
+            .expect("LSP session should succeed");
+
+        let output = String::from_utf8(output).expect("Output should be UTF-8");
+        let messages: Vec<serde_json::Value> = output
+            .split("Content-Length: ")
+            .skip(1)
+            .map(|m| {
+                let (_, body) = m.split_once("\r\n\r\n").expect("Message has a header");
+                serde_json::from_str(body).expect("Message body is JSON")
+            })
+            .collect();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(
+            messages[0]["result"]["capabilities"]["codeActionProvider"],
+            json!({"codeActionKinds": ["quickfix"]})
+        );
+        assert_eq!(messages[1]["method"], "textDocument/publishDiagnostics");
+        assert_eq!(messages[1]["params"]["uri"], json!(uri));
+        let diagnostics = &messages[1]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().map(Vec::len), Some(4));
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({"start": {"line": 1, "character": 9}, "end": {"line": 1, "character": 15}})
+        );
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["source"], "terraform validate");
+        let actions = &messages[2]["result"];
+        assert_eq!(actions.as_array().map(Vec::len), Some(1));
+        assert_eq!(actions[0]["kind"], "quickfix");
+        assert_eq!(
+            actions[0]["edit"]["changes"][&uri],
+            json!([{
+                "range": {"start": {"line": 1, "character": 9}, "end": {"line": 1, "character": 15}},
+                "newText": "list(string)",
+            }])
+        );
+        assert_eq!(
+            messages[3],
+            json!({"jsonrpc": "2.0", "id": 3, "result": null})
+        );
+    }
+
+    #[test]
+    fn test_watch_report() {
+        let opt = Opt::from_iter(&["tfv2rd", "--format", "rdjson", "watch", "testdata"]);
+        let mut outputs = BTreeMap::new();
+        outputs.insert(
+            PathBuf::from("./testdata/suggestions"),
+            Ok(TF_SUGGESTIONS.to_owned()),
+        );
+        let path = std::env::temp_dir().join(format!("tfv2rd-watch-{}.json", process::id()));
+        watch::report(&opt, &outputs, None, Some(&path)).expect("Report should be written");
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).expect("Report should be readable"))
+                .expect("Report should be JSON");
+        fs::remove_file(&path).expect("Report should be removed");
+        assert_eq!(report["severity"], "ERROR");
+        let diagnostics = report["diagnostics"]
+            .as_array()
+            .expect("Diagnostics are a list");
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d["location"]["path"] == "testdata/suggestions/main.tf"));
+        assert_eq!(
+            diagnostics[0]["suggestions"][0]["text"],
+            json!("list(string)")
+        );
+    }
+
+    #[test]
+    fn test_watch_validate_failure() {
+        let opt = Opt::from_iter(&["tfv2rd", "--format", "rdjson", "watch", "testdata"]);
+        // terraform can't be run the first time, and then validates
+        let runs = Rc::new(Cell::new(0));
+        let validate_runs = runs.clone();
+        let mut watcher = watch::Watcher::new(
+            &opt,
+            None,
+            Box::new(move |_| {
+                validate_runs.set(validate_runs.get() + 1);
+                if validate_runs.get() == 1 {
+                    Err(Error::RunCommand {
+                        command: "terraform validate -json".to_owned(),
+                        source: io::Error::new(io::ErrorKind::NotFound, "not found"),
+                    })
+                } else {
+                    Ok(TF_SUGGESTIONS.to_owned())
+                }
+            }),
+        );
+        let changed: BTreeSet<PathBuf> = [PathBuf::from("testdata/suggestions/main.tf")].into();
+        let path = std::env::temp_dir().join(format!("tfv2rd-watch-fail-{}.json", process::id()));
+        let mut update = || {
+            watcher
+                .update(&changed, Some(&path))
+                .expect("Watching should go on");
+            let report: serde_json::Value = serde_json::from_str(
+                &fs::read_to_string(&path).expect("Report should be readable"),
+            )
+            .expect("Report should be JSON");
+            report
+        };
+
+        let report = update();
+        assert_eq!(report["severity"], "ERROR");
+        assert_eq!(
+            report["diagnostics"],
+            json!([{
+                "message": "Can't run `terraform validate -json`: not found",
+                "location": {"path": "testdata/suggestions"},
+                "severity": "ERROR",
+                "source": {"name": "terraform validate"}
+            }])
+        );
+
+        let report = update();
+        fs::remove_file(&path).expect("Report should be removed");
+        assert_eq!(runs.get(), 2);
+        let diagnostics = report["diagnostics"]
+            .as_array()
+            .expect("Diagnostics are a list");
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d["location"]["path"] == "testdata/suggestions/main.tf"));
+    }
+
+    #[test]
+    fn test_bitbucket_output() {
+        let tf_result =
+            parse_validate_result(TF_SUGGESTIONS, false).expect("Test data should be parsed");
+        let sources = SourceFiles::new(Some("testdata/suggestions".into()));
+        let bitbucket = |format| {
+            let all_diags = convert(
+                &tf_result.diagnostics,
+                &Box::new(passthru_path),
+                &sources,
+                None,
+                false,
+                test_source("terraform validate"),
+            )
+            .expect("Test data should be converted");
+            let mut output = Vec::new();
+            write_output(
+                &mut output,
+                &format,
+                all_diags,
+                rd::Severity::Error,
+                test_source("terraform validate"),
+                FailOn::Error,
+                false,
+            )
+            .expect("Output should be written");
+            serde_json::from_slice::<serde_json::Value>(&output).expect("Output should be JSON")
+        };
+        let keys = |object: &serde_json::Value| {
+            let mut keys: Vec<String> = object
+                .as_object()
+                .expect("Payloads are objects")
+                .keys()
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        };
+        let data = json!([
+            {"title": "Errors", "type": "NUMBER", "value": 1},
+            {"title": "Warnings", "type": "NUMBER", "value": 3},
+            {"title": "Total", "type": "NUMBER", "value": 4},
+        ]);
+
+        let cloud = bitbucket(OutputFormat::Bitbucket);
+        assert_eq!(
+            cloud["report"],
+            json!({
+                "title": "terraform validate",
+                "details": "terraform validate found 1 error(s) and 3 warning(s).",
+                "reporter": "tfv2rd",
+                "report_type": "BUG",
+                "result": "FAILED",
+                "data": data,
+            })
+        );
+        let annotations = cloud["annotations"]
+            .as_array()
+            .expect("Annotations are a list");
+        assert_eq!(annotations.len(), 4);
+        assert_eq!(
+            keys(&annotations[0]),
+            [
+                "annotation_type",
+                "details",
+                "external_id",
+                "line",
+                "path",
+                "severity",
+                "summary"
+            ]
+        );
+        assert_eq!(annotations[0]["path"], "main.tf");
+        assert_eq!(annotations[0]["line"], 2);
+        assert_eq!(annotations[0]["severity"], "HIGH");
+        assert_eq!(annotations[0]["annotation_type"], "BUG");
+        assert_eq!(annotations[1]["annotation_type"], "CODE_SMELL");
+        let ids: std::collections::HashSet<_> = annotations
+            .iter()
+            .map(|a| a["external_id"].as_str())
+            .collect();
+        assert_eq!(ids.len(), 4);
+
+        let server = bitbucket(OutputFormat::BitbucketServer);
+        assert_eq!(
+            server["report"],
+            json!({
+                "title": "terraform validate",
+                "details": "terraform validate found 1 error(s) and 3 warning(s).",
+                "reporter": "tfv2rd",
+                "result": "FAIL",
+                "data": data,
+            })
+        );
+        let server_annotation = &server["annotations"][0];
+        assert_eq!(
+            keys(server_annotation),
+            ["externalId", "line", "message", "path", "severity", "type"]
+        );
+        assert_eq!(
+            server_annotation["externalId"],
+            annotations[0]["external_id"]
+        );
+        assert_eq!(server_annotation["message"], annotations[0]["summary"]);
+        assert_eq!(server_annotation["type"], "BUG");
+
+        // Ids don't depend on line numbers, so they stay the same when lines are added above
+        let mut moved = convert(
+            &tf_result.diagnostics[..1],
+            &Box::new(passthru_path),
+            &sources,
+            None,
+            false,
+            test_source("terraform validate"),
+        )
+        .expect("Test data should be converted");
+        moved[0].location.range.as_mut().unwrap().start.line = Some(12);
+        let moved = bitbucket::cloud(
+            &moved,
+            rd::Severity::Error,
+            test_source("terraform validate"),
+        );
+        assert_eq!(
+            json!(moved.annotations[0].external_id),
+            annotations[0]["external_id"]
+        );
+    }
+
+    #[test]
+    fn test_azure_output() {
+        let tf_result =
+            parse_validate_result(TF_SUGGESTIONS, false).expect("Test data should be parsed");
+        let azure = |fail_on| {
+            let all_diags = convert(
+                &tf_result.diagnostics,
+                &Box::new(passthru_path),
+                &SourceFiles::new(None),
+                None,
+                false,
+                test_source("terraform validate"),
+            )
+            .expect("Test data should be converted");
+            let mut output = Vec::new();
+            write_output(
+                &mut output,
+                &OutputFormat::Azure,
+                all_diags,
+                rd::Severity::Error,
+                test_source("terraform validate"),
+                fail_on,
+                false,
+            )
+            .expect("Output should be written");
+            String::from_utf8(output).expect("Output should be UTF-8")
+        };
+        let output = azure(FailOn::Error);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "##vso[task.logissue type=error;sourcepath=main.tf;linenumber=2;columnnumber=10;]Invalid quoted type constraints"
+        );
+        assert_eq!(
+            lines[1],
+            "##vso[task.logissue type=warning;sourcepath=main.tf;linenumber=10;columnnumber=10;]Interpolation-only expressions are deprecated"
+        );
+        assert_eq!(
+            lines[4],
+            "##vso[task.complete result=Failed;]1 error(s), 3 warning(s)"
+        );
+        assert!(azure(FailOn::Never).ends_with(
+            "##vso[task.complete result=SucceededWithIssues;]1 error(s), 3 warning(s)\n"
+        ));
+        assert_eq!(
+            "warn".parse::<FailOn>(),
+            Err("Unknown severity to fail on 'warn'".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_sonarqube_output() {
+        let tf_result = parse_validate_result(TF_TYPOS, false).expect("Test data should be parsed");
+        let schema = schema::load(Path::new("testdata/provider_schema.json"))
+            .expect("Provider schema should be loaded");
+        let all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(Some("testdata/typos".into())),
+            Some(&schema),
+            false,
+            test_source("terraform validate"),
+        )
+        .expect("Test data should be converted");
+        let mut output = Vec::new();
+        write_output(
+            &mut output,
+            &OutputFormat::SonarQube,
+            all_diags,
+            rd::Severity::Error,
+            test_source("terraform validate"),
+            FailOn::Error,
+            false,
+        )
+        .expect("Output should be written");
+        let report: serde_json::Value =
+            serde_json::from_slice(&output).expect("Output should be JSON");
+        let issues = report["issues"].as_array().expect("Issues are a list");
+        assert_eq!(issues.len(), tf_result.diagnostics.len());
+        assert_eq!(
+            issues[0],
+            json!({
+                "engineId": "terraform validate",
+                "ruleId": "unsupported-argument",
+                "severity": "CRITICAL",
+                "type": "BUG",
+                "primaryLocation": {
+                    "message": issues[0]["primaryLocation"]["message"],
+                    "filePath": "main.tf",
+                    "textRange": {"startLine": 3, "endLine": 3, "startColumn": 2, "endColumn": 15},
+                },
+            })
+        );
+        assert!(issues[0]["primaryLocation"]["message"]
+            .as_str()
+            .is_some_and(|m| m.contains("did you mean")));
+        // Each rule is listed once, however many issues there are for it
+        let rules = report["rules"].as_array().expect("Rules are a list");
+        let rule_ids: Vec<&str> = rules.iter().filter_map(|r| r["id"].as_str()).collect();
+        let mut unique = rule_ids.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(rule_ids.len(), unique.len());
+        // The rules come from Terraform's summaries, not the messages with suggestions added
+        let mut summaries: Vec<&str> = tf_result
+            .diagnostics
+            .iter()
+            .map(|d| d.summary.as_ref())
+            .collect();
+        summaries.sort_unstable();
+        summaries.dedup();
+        assert_eq!(rules.len(), summaries.len());
+        assert_eq!(rules[0]["name"], "Unsupported argument");
+        assert_eq!(
+            rules[0]["impacts"],
+            json!([{"softwareQuality": "RELIABILITY", "severity": "HIGH"}])
+        );
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let tf_result =
+            parse_validate_result(TF_NO_RANGE, false).expect("Test data should be parsed");
+        let plain = |end_position| {
+            let all_diags = convert(
+                &tf_result.diagnostics,
+                &Box::new(passthru_path),
+                &SourceFiles::new(None),
+                None,
+                false,
+                test_source("terraform validate"),
+            )
+            .expect("Test data should be converted");
+            let mut output = Vec::new();
+            write_output(
+                &mut output,
+                &OutputFormat::Plain,
+                all_diags,
+                rd::Severity::Error,
+                test_source("terraform validate"),
+                FailOn::Error,
+                end_position,
+            )
+            .expect("Output should be written");
+            String::from_utf8(output).expect("Output should be UTF-8")
+        };
+        assert_eq!(
+            plain(false),
+            "variables.tf:8:17: error: Invalid quoted type constraints\n  Terraform 0.11 and earlier required type constraints to be given in quotes, but that form is now deprecated and will be removed in a future version of Terraform. Remove the quotes around \"string\".\n"
+        );
+        assert!(plain(true).starts_with("variables.tf:8:17-8:25: error: "));
+    }
+
+    #[test]
+    fn test_codeowners() {
+        let codeowners = codeowners::parse(Path::new("/repo"), CODEOWNERS);
+        assert_eq!(
+            codeowners.owners("main.tf"),
+            ["@example/platform", "@example/security"]
+        );
+        assert_eq!(
+            codeowners.owners("/repo/modules/lb/outputs.tf"),
+            ["@example/networking", "@alice", "@example/security"]
+        );
+        assert_eq!(
+            codeowners.owners("modules/lb/variables.tf"),
+            ["@example/networking", "@example/security"]
+        );
+        assert_eq!(codeowners.owners("docs/README.md"), ["docs@example.com"]);
+        assert_eq!(codeowners.owners("sub/docs"), ["@example/platform"]);
+        assert!(codeowners.owners("/elsewhere/main.tf").is_empty());
+
+        let stream = |args: &[&str]| {
+            let opt = Opt::from_iter([&["tfv2rd", "--input", "stream"], args].concat());
+            let mut output = Vec::new();
+            let summary = stream_rdjsonl(
+                &mut TF_PLAN_STREAM.as_bytes(),
+                &mut output,
+                &opt,
+                &make_path_converter(&opt).expect("can create path_converter"),
+                &SourceFiles::new(None),
+                None,
+                Some(&codeowners),
+            )
+            .expect("Test data should be converted");
+            let messages: Vec<String> = output
+                .lines()
+                .map(|line| {
+                    let diag: serde_json::Value =
+                        serde_json::from_str(&line.expect("Output should be UTF-8"))
+                            .expect("Output should be JSON");
+                    diag["message"].as_str().unwrap_or_default().to_owned()
+                })
+                .collect();
+            (messages, summary)
+        };
+
+        let (messages, summary) = stream(&["--codeowners"]);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with(" (owned by @example/platform, @example/security)"));
+        assert_eq!(summary.by_owner.get("@example/security"), Some(&2));
+        assert_eq!(summary.by_owner.get("@alice"), Some(&1));
+
+        let (messages, summary) = stream(&["--owner", "@ALICE"]);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("@alice"));
+        assert_eq!(summary.skipped_other_owners, 1);
+    }
+
+    #[test]
+    fn test_codeowners_keep_identities() {
+        let tf_result =
+            parse_validate_result(TF_QUOTING, false).expect("Test data should be parsed");
+        let codeowners = codeowners::parse(Path::new("/repo"), "* @team/a\n");
+        let output = |format, owned: bool| {
+            let mut all_diags = convert(
+                &tf_result.diagnostics,
+                &Box::new(passthru_path),
+                &SourceFiles::new(None),
+                None,
+                false,
+                test_source("terraform validate"),
+            )
+            .expect("Test data should be converted");
+            if owned {
+                for diag in &mut all_diags {
+                    codeowners.attribute(diag);
+                }
+            }
+            let mut output = Vec::new();
+            write_output(
+                &mut output,
+                &format,
+                all_diags,
+                rd::Severity::Error,
+                test_source("terraform validate"),
+                FailOn::Error,
+                false,
+            )
+            .expect("Output should be written");
+            serde_json::from_slice::<serde_json::Value>(&output).expect("Output should be JSON")
+        };
+
+        // Rules and annotation ids don't change with the owners of files
+        let (plain, owned) = (
+            output(OutputFormat::SonarQube, false),
+            output(OutputFormat::SonarQube, true),
+        );
+        assert_eq!(owned["rules"], plain["rules"]);
+        assert_eq!(owned["issues"][0]["ruleId"], plain["issues"][0]["ruleId"]);
+        let (plain, owned) = (
+            output(OutputFormat::Bitbucket, false),
+            output(OutputFormat::Bitbucket, true),
+        );
+        assert_eq!(
+            owned["annotations"][0]["external_id"],
+            plain["annotations"][0]["external_id"]
+        );
+
+        // But the reviewdog formats name them
+        let owned = output(OutputFormat::RdJson, true);
+        assert_eq!(
+            owned["diagnostics"][0]["message"],
+            "Invalid quoted type constraints (owned by @team/a)"
+        );
+    }
+
+    #[test]
+    fn test_protobuf_round_trip() {
+        use protobuf::decode;
+
+        let tf_result =
+            parse_validate_result(TF_SUGGESTIONS, false).expect("Test data should be parsed");
+        let source = rd::Source {
+            name: "terraform validate",
+            url: Some("https://ci.example.com/job/7"),
+        };
+        let convert_all = || {
+            let mut all_diags = convert(
+                &tf_result.diagnostics,
+                &Box::new(passthru_path),
+                &SourceFiles::new(Some("testdata/suggestions".into())),
+                None,
+                false,
+                source,
+            )
+            .expect("Test data should be converted");
+            all_diags[1].code = Some(rd::Code {
+                value: Cow::Borrowed("R001"),
+                url: Some(Cow::Borrowed("https://example.com/rules/R001")),
+            });
+            // Including a deletion, suggesting empty text
+            let (fmt_diags, _) = fmtdiff::convert(
+                TF_FMT_DIFF,
+                &Box::new(passthru_path),
+                false,
+                source,
+                Engine::Terraform,
+            )
+            .expect("Test data should be converted");
+            all_diags.extend(fmt_diags);
+            all_diags
+        };
+        let write = |format| {
+            let mut output = Vec::new();
+            write_output(
+                &mut output,
+                &format,
+                convert_all(),
+                rd::Severity::Error,
+                source,
+                FailOn::Error,
+                false,
+            )
+            .expect("Output should be written");
+            output
+        };
+
+        let output = write(OutputFormat::RdPbStream);
+        let messages = decode::split_delimited(&output);
+        let all_diags = convert_all();
+        assert_eq!(messages.len(), all_diags.len());
+        assert!(all_diags
+            .iter()
+            .flat_map(|d| &d.suggestions)
+            .any(|s| s.text.is_empty()));
+        for (message, diag) in messages.into_iter().zip(&all_diags) {
+            assert_eq!(
+                decode::message(message, decode::DIAGNOSTIC),
+                decode::with_defaults(
+                    serde_json::to_value(diag).expect("Diagnostic can be serialized"),
+                    decode::DIAGNOSTIC
+                )
+            );
+        }
+
+        let output = write(OutputFormat::RdPb);
+        let messages = decode::split_delimited(&output);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            decode::message(messages[0], decode::DIAGNOSTIC_RESULT),
+            decode::with_defaults(
+                serde_json::to_value(rd::DiagnosticResult {
+                    diagnostics: convert_all(),
+                    source: Some(source),
+                    severity: Some(rd::Severity::Error),
+                })
+                .expect("DiagnosticResult can be serialized"),
+                decode::DIAGNOSTIC_RESULT
+            )
+        );
+    }
+
+    #[test]
+    fn test_stream_rejects_non_json_unless_lenient() {
+        let line = "2021-10-08T10:12:41.000Z [DEBUG] provider: starting plugin";
+        assert!(parse_stream_line(line, false).is_err());
+        assert!(parse_stream_line(line, true)
+            .expect("Lenient parsing skips bad lines")
+            .is_none());
+    }
+
+    fn ranges(all_diags: &[rd::Diagnostic]) -> serde_json::Value {
+        serde_json::to_value(
+            all_diags
+                .iter()
+                .map(|d| &d.location.range)
+                .collect::<Vec<_>>(),
+        )
+        .expect("Ranges should be serialized")
+    }
+
+    #[test]
+    fn test_multibyte_columns() {
+        let mut result: tf::ValidateResult =
+            serde_json::from_str(TF_MULTIBYTE).expect("Test data should be parsed");
+        let expected = json!([
+            {"start": {"line": 2, "column": 17}, "end": {"line": 2, "column": 49}},
+            {"start": {"line": 7, "column": 21}, "end": {"line": 7, "column": 32}},
+        ]);
+
+        // Using the byte offsets into the source file
+        let sources = SourceFiles::new(Some("testdata/multibyte".into()));
+        let all_diags = convert(
+            &result.diagnostics,
+            &Box::new(passthru_path),
+            &sources,
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        assert_eq!(ranges(&all_diags), expected);
+
+        // Using the snippets when the source file can't be read
+        let sources = SourceFiles::new(None);
+        let all_diags = convert(
+            &result.diagnostics,
+            &Box::new(passthru_path),
+            &sources,
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        assert_eq!(ranges(&all_diags), expected);
+
+        // Falling back to Terraform's columns with neither
+        for diag in result.diagnostics.iter_mut() {
+            diag.snippet = None;
+        }
+        let all_diags = convert(
+            &result.diagnostics,
+            &Box::new(passthru_path),
+            &sources,
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        assert_eq!(
+            ranges(&all_diags),
+            json!([
+                {"start": {"line": 2, "column": 17}, "end": {"line": 2, "column": 42}},
+                {"start": {"line": 7, "column": 19}, "end": {"line": 7, "column": 30}},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_byte_column_ignores_mismatched_file() {
+        let pos = tf::SourcePosition {
+            byte: 129,
+            line: 7,
+            column: 19,
+        };
+        // The byte offset is on a different line of this file, so it can't be trusted
+        assert_eq!(sources::byte_column(&pos, Some("ñandú\n"), None), 19);
+        assert_eq!(sources::byte_column(&pos, Some("short"), None), 19);
+    }
+
+    #[test]
+    fn test_enrich_missing_positions() {
+        let mut result: tf::ValidateResult =
+            serde_json::from_str(TF_MISSING_END).expect("Test data should be parsed");
+        let sources = SourceFiles::new(Some("testdata/multibyte".into()));
+        for diag in result.diagnostics.iter_mut() {
+            enrich::enrich(diag, &sources);
+        }
+        let all_diags = convert(
+            &result.diagnostics,
+            &Box::new(passthru_path),
+            &sources,
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        assert_eq!(
+            ranges(&all_diags),
+            json!([
+                {"start": {"line": 7, "column": 21}, "end": {"line": 7, "column": 32}},
+                {"start": {"line": 2, "column": 17}, "end": {"line": 2, "column": 49}},
+                {"start": {"line": 7, "column": 3}, "end": {"line": 7, "column": 32}},
+            ])
+        );
+
+        let snippet = result.diagnostics[0]
+            .snippet
+            .as_ref()
+            .expect("A snippet should be synthesised");
+        assert_eq!(snippet.code, "  name = \"ñandú-${var.missing}\"");
+        assert_eq!(snippet.start_line, 7);
+        assert_eq!(
+            &snippet.code
+                [snippet.highlight_start_offset as usize..snippet.highlight_end_offset as usize],
+            "var.missing"
+        );
+    }
+
+    #[test]
+    fn test_enrich_without_source_file() {
+        let mut result: tf::ValidateResult =
+            serde_json::from_str(TF_MISSING_END).expect("Test data should be parsed");
+        let sources = SourceFiles::new(None);
+        for diag in result.diagnostics.iter_mut() {
+            enrich::enrich(diag, &sources);
+        }
+        let range = result.diagnostics[0].range.as_ref().unwrap();
+        assert!(range.end.is_none());
+        assert!(result.diagnostics[0].snippet.is_none());
+    }
+
+    #[test]
+    fn test_multiline_suggestions() {
+        let tf_result =
+            parse_validate_result(TF_SUGGESTIONS, false).expect("Test data should be parsed");
+        let sources = SourceFiles::new(Some("testdata/suggestions".into()));
+        let all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &sources,
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        let suggestions = all_diags
+            .iter()
+            .map(|d| {
+                serde_json::to_value(&d.suggestions).expect("Suggestions should be serialized")
+            })
+            .collect::<Vec<_>>();
+        let at = |line, column| json!({"line": line, "column": column});
+        assert_eq!(
+            suggestions,
+            vec![
+                json!([{"range": {"start": at(2, 10), "end": at(2, 16)}, "text": "list(string)"}]),
+                json!([{"range": {"start": at(10, 10), "end": at(10, 22)}, "text": "var.ami"}]),
+                json!([{
+                    "range": {"start": at(11, 10), "end": at(14, 6)},
+                    "text": "merge(\n    var.common_tags,\n    { Name = \"web\" },\n  )"
+                }]),
+                // The snippet is from a different version of the file
+                json!([]),
+            ]
+        );
+
+        // Without the source file nothing can be checked, so nothing is suggested
+        let all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(None),
+            None,
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        assert!(all_diags.iter().all(|d| d.suggestions.is_empty()));
+    }
+
+    #[test]
+    fn test_provider_schema_suggestions() {
+        let schema = schema::load(Path::new("testdata/provider_schema.json"))
+            .expect("Test schema should be loaded");
+        let tf_result = parse_validate_result(TF_TYPOS, false).expect("Test data should be parsed");
+        let all_diags = convert(
+            &tf_result.diagnostics,
+            &Box::new(passthru_path),
+            &SourceFiles::new(Some("testdata/typos".into())),
+            Some(&schema),
+            false,
+            test_source("test"),
+        )
+        .expect("Test data should be converted");
+        let corrections = all_diags
+            .iter()
+            .map(|d| {
+                (
+                    d.message.as_ref(),
+                    d.suggestions.first().map(|s| s.text.as_ref()),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            corrections,
+            vec![
+                (
+                    "Unsupported argument, did you mean \"instance_type\"?",
+                    Some("instance_type")
+                ),
+                (
+                    "Unsupported argument, did you mean \"depends_on\"?",
+                    Some("depends_on")
+                ),
+                (
+                    "Unsupported argument, did you mean \"volume_size\"?",
+                    Some("volume_size")
+                ),
+                (
+                    "Unsupported block type, did you mean \"ebs_block_device\"?",
+                    Some("ebs_block_device")
+                ),
+                ("Unsupported argument", None),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&all_diags[0].suggestions[0].range).unwrap(),
+            json!({"start": {"line": 3, "column": 3}, "end": {"line": 3, "column": 16}})
+        );
+
+        match schema::load(Path::new("testdata/quoting.json")) {
+            Err(e @ Error::ParseFile { .. }) => assert_eq!(e.exit_code(), 65),
+            other => panic!("Expected a ParseFile error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_fmt_diff_suggestions() {
+        let (all_diags, _) = fmtdiff::convert(
+            TF_FMT_DIFF,
+            &Box::new(passthru_path),
+            false,
+            test_source("test_fmt"),
+            Engine::Terraform,
+        )
+        .expect("Test data should be converted");
+        let lines = |start, end| json!({"start": {"line": start}, "end": {"line": end}});
+        let code = json!({
+            "value": "fmt",
+            "url": "https://developer.hashicorp.com/terraform/cli/commands/fmt"
+        });
+        assert_eq!(
+            serde_json::to_value(all_diags).expect("Converted data should be serialized"),
+            json!([
+                {
+                    "message": "Incorrect formatting, run `terraform fmt` to fix",
+                    "location": {"path": "main.tf", "range": lines(2, 2)},
+                    "severity": "WARNING",
+                    "source": {"name": "test_fmt"},
+                    "code": code,
+                    "suggestions": [{"range": lines(2, 2), "text": "  ami           = \"ami-123456\""}]
+                },
+                {
+                    "message": "Incorrect formatting, run `terraform fmt` to fix",
+                    "location": {"path": "main.tf", "range": lines(5, 5)},
+                    "severity": "WARNING",
+                    "source": {"name": "test_fmt"},
+                    "code": code,
+                    "suggestions": [{"range": lines(5, 5), "text": "    Name = \"web\""}]
+                },
+                {
+                    "message": "Incorrect formatting, run `terraform fmt` to fix",
+                    "location": {"path": "modules/lb/outputs.tf", "range": lines(3, 3)},
+                    "severity": "WARNING",
+                    "source": {"name": "test_fmt"},
+                    "code": code,
+                    "suggestions": [{
+                        "range": {"start": {"line": 3, "column": 1}, "end": {"line": 4, "column": 1}},
+                        "text": ""
+                    }]
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_fmt_diff_insertion_and_errors() {
+        let diff = "--- old/a.tf\n+++ new/a.tf\n@@ -1,2 +1,3 @@\n a = 1\n+b = 2\n c = 3\n";
+        let (all_diags, _) = fmtdiff::convert(
+            diff,
+            &Box::new(passthru_path),
+            false,
+            test_source("test_fmt"),
+            Engine::Terraform,
+        )
+        .expect("Test data should be converted");
+        assert_eq!(
+            serde_json::to_value(&all_diags[0].suggestions).unwrap(),
+            json!([{
+                "range": {"start": {"line": 2, "column": 1}, "end": {"line": 2, "column": 1}},
+                "text": "b = 2\n"
+            }])
+        );
+
+        let truncated = "--- old/a.tf\n+++ new/a.tf\n@@ -1,2 +1,3 @@\n a = 1\n";
+        match fmtdiff::convert(
+            truncated,
+            &Box::new(passthru_path),
+            false,
+            test_source("test_fmt"),
+            Engine::Terraform,
+        ) {
+            Err(Error::MalformedDiff { .. }) => {}
+            other => panic!("Expected a MalformedDiff error, got {:?}", other),
+        }
+
+        assert!(fmtdiff::convert(
+            "",
+            &Box::new(passthru_path),
+            false,
+            test_source("test_fmt"),
+            Engine::Terraform
+        )
+        .expect("No differences should be converted")
+        .0
+        .is_empty());
+
+        // Changes to files whose paths can't be converted are counted as skipped
+        let (all_diags, skipped) = fmtdiff::convert(
+            TF_FMT_DIFF,
+            &Box::new(|filename: &str| match filename {
+                "main.tf" => Err(Error::PathConversion {
+                    path: filename.to_owned(),
+                    reason: "test".to_owned(),
+                }),
+                _ => Ok(filename.to_owned()),
+            }),
+            true,
+            test_source("test_fmt"),
+            Engine::Terraform,
+        )
+        .expect("Test data should be converted");
+        assert_eq!((all_diags.len(), skipped), (1, 2));
+    }
+
+    #[test]
+    fn test_tfsec_findings() {
+        let report: tfsec::Report =
+            serde_json::from_str(TFSEC).expect("Test data should be parsed");
+        let path_converter = make_path_converter(&Opt::from_iter(&[
+            "tfv2rd",
+            "--workdir",
+            "/home/runner/work/infra/infra/stacks/logging",
+            "--basedir",
+            "/home/runner/work/infra/infra",
+        ]))
+        .expect("can create path_converter");
+        let all_diags = scanner::convert(
+            report.findings(&SeverityMap::default()),
+            &path_converter,
+            false,
+            test_source("test_tfsec"),
+        )
+        .expect("Test data should be converted");
+        assert_eq!(
+            serde_json::to_value(&all_diags[0]).expect("Converted data should be serialized"),
+            json!({
+                "message": "No public access block so not blocking public acls",
+                "location": {
+                    "path": "stacks/logging/main.tf",
+                    "range": {"start": {"line": 1}, "end": {"line": 4}}
+                },
+                "severity": "ERROR",
+                "source": {"name": "test_tfsec"},
+                "code": {
+                    "value": "AVD-AWS-0086",
+                    "url": "https://aquasecurity.github.io/tfsec/v1.28.1/checks/aws/s3/block-public-acls/"
+                },
+                "original_output": "aws-s3-block-public-acls: Enable blocking any PUT calls with a public ACL specified"
+            })
+        );
+        assert_eq!(all_diags[1].severity, Some(rd::Severity::Warning));
+
+        let report: tfsec::Report =
+            serde_json::from_str(r#"{"results": null}"#).expect("Empty results should be parsed");
+        assert!(report.findings(&SeverityMap::default()).is_empty());
+        assert!(
+            serde_json::from_str::<tfsec::Report>(TF_QUOTING).is_err(),
+            "Other JSON shouldn't be taken for a report without results"
+        );
+    }
+
+    #[test]
+    fn test_trivy_findings() {
+        let report: trivy::Report =
+            serde_json::from_str(TRIVY).expect("Test data should be parsed");
+        let severities: SeverityMap = "high=warning, LOW=error"
+            .parse()
+            .expect("Map should be parsed");
+        let all_diags = scanner::convert(
+            report.findings(&severities),
+            &Box::new(passthru_path),
+            false,
+            test_source("test_trivy"),
+        )
+        .expect("Test data should be converted");
+        assert_eq!(all_diags.len(), 2, "Passed checks should be left out");
+        assert_eq!(
+            serde_json::to_value(&all_diags).expect("Converted data should be serialized"),
+            json!([
+                {
+                    "message": "No public access block so not blocking public acls",
+                    "location": {
+                        "path": "stacks/logging/main.tf",
+                        "range": {"start": {"line": 1}, "end": {"line": 4}}
+                    },
+                    "severity": "WARNING",
+                    "source": {"name": "test_trivy"},
+                    "code": {
+                        "value": "AVD-AWS-0086",
+                        "url": "https://avd.aquasec.com/misconfig/avd-aws-0086"
+                    },
+                    "original_output": "Enable blocking any PUT calls with a public ACL specified"
+                },
+                {
+                    "message": "Bucket does not restrict public buckets",
+                    "location": {"path": "stacks/logging/main.tf"},
+                    "severity": "ERROR",
+                    "source": {"name": "test_trivy"},
+                    "code": {
+                        "value": "AVD-AWS-0093",
+                        "url": "https://docs.aws.amazon.com/AmazonS3/latest/dev/access-control-block-public-access.html"
+                    },
+                    "original_output": "Limit the access to public buckets to only the owner or AWS Services (eg; CloudFront)"
+                }
+            ])
+        );
+        assert!("HIGH=fatal".parse::<SeverityMap>().is_err());
+
+        let empty: trivy::Report =
+            serde_json::from_str(r#"{"SchemaVersion": 2, "ArtifactName": "."}"#)
+                .expect("A report without results should be parsed");
+        assert!(empty.findings(&severities).is_empty());
+        assert!(
+            serde_json::from_str::<trivy::Report>(TF_QUOTING).is_err(),
+            "Other JSON shouldn't be taken for a report without results"
+        );
+    }
+
+    #[test]
+    fn test_checkov_findings() {
+        let reports = checkov::parse(CHECKOV).expect("Test data should be parsed");
+        let path_converter = make_path_converter(&Opt::from_iter(&[
+            "tfv2rd",
+            "--workdir",
+            "/home/runner/work/infra/infra",
+            "--basedir",
+            "/home/runner/work/infra/infra",
+        ]))
+        .expect("can create path_converter");
+        let all_diags = scanner::convert(
+            checkov::findings(reports, &SeverityMap::default(), true),
+            &path_converter,
+            false,
+            test_source("test_checkov"),
+        )
+        .expect("Test data should be converted");
+        assert_eq!(
+            serde_json::to_value(&all_diags).expect("Converted data should be serialized"),
+            json!([
+                {
+                    "message": "Ensure the S3 bucket has access logging enabled",
+                    "location": {
+                        "path": "stacks/logging/main.tf",
+                        "range": {"start": {"line": 1}, "end": {"line": 4}}
+                    },
+                    "severity": "ERROR",
+                    "source": {"name": "test_checkov"},
+                    "code": {
+                        "value": "CKV_AWS_18",
+                        "url": "https://docs.bridgecrew.io/docs/s3_13-enable-logging"
+                    },
+                    "original_output": "Resource: aws_s3_bucket.logs"
+                },
+                {
+                    "message": "AWS Access Key",
+                    "location": {
+                        "path": "stacks/network/provider.tf",
+                        "range": {"start": {"line": 7}, "end": {"line": 8}}
+                    },
+                    "severity": "ERROR",
+                    "source": {"name": "test_checkov"},
+                    "code": {
+                        "value": "CKV_SECRET_2",
+                        "url": "https://docs.bridgecrew.io/docs/git_secrets_2"
+                    },
+                    "original_output": "Resource: 25910f981e85ca04baf359199dd0bd4a3ae738b6"
+                }
+            ])
+        );
+
+        // Without a base directory, paths are relative to the scanned directory
+        let reports = checkov::parse(CHECKOV).expect("Test data should be parsed");
+        let paths: Vec<_> = checkov::findings(reports, &SeverityMap::default(), false)
+            .into_iter()
+            .map(|finding| finding.filename)
+            .collect();
+        assert_eq!(
+            paths,
+            ["stacks/logging/main.tf", "stacks/network/provider.tf"]
+        );
+
+        // A single report, and the summary Checkov outputs when there's nothing to scan
+        let single =
+            checkov::parse(r#"{"check_type": "terraform", "results": {"failed_checks": []}}"#)
+                .expect("A single report should be parsed");
+        assert_eq!(single.len(), 1);
+        let empty = checkov::parse(
+            r#"{"passed": 0, "failed": 0, "skipped": 0, "parsing_errors": 0, "resource_count": 0}"#,
+        )
+        .expect("An empty summary should be parsed");
+        assert!(checkov::findings(empty, &SeverityMap::default(), false).is_empty());
+        assert!(
+            checkov::parse(TF_QUOTING).is_err(),
+            "Other JSON shouldn't be taken for an empty summary"
+        );
+    }
+
+    #[test]
+    fn test_terraform_test_results() {
+        let opt = Opt::from_iter(&["tfv2rd", "--input", "test"]);
+        let mut output = Vec::new();
+        let summary = stream_rdjsonl(
+            &mut TF_TEST_OUTPUT.as_bytes(),
+            &mut output,
+            &opt,
+            &make_path_converter(&opt).expect("can create path_converter"),
+            &SourceFiles::new(None),
+            None,
+            None,
+        )
+        .expect("Test data should be converted");
+        let lines = std::str::from_utf8(&output)
+            .expect("Output should be UTF-8")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("Each line should be JSON"))
+            .collect::<Vec<serde_json::Value>>();
+        assert_eq!(
+            lines,
+            vec![
+                json!({
+                    "message": "Test assertion failed",
+                    "location": {
+                        "path": "tests/buckets.tftest.hcl",
+                        "range": {
+                            "start": {"line": 14, "column": 17},
+                            "end": {"line": 14, "column": 62}
+                        }
+                    },
+                    "severity": "ERROR",
+                    "source": {"name": "terraform test"},
+                    "original_output": "S3 bucket name did not match expected\n\n(in run \"bucket_name\")"
+                }),
+                json!({
+                    "message": "1 of 3 test runs failed",
+                    "location": {"path": "tests/buckets.tftest.hcl"},
+                    "severity": "ERROR",
+                    "source": {"name": "terraform test"},
+                    "code": {
+                        "value": "test",
+                        "url": "https://developer.hashicorp.com/terraform/cli/commands/test"
+                    },
+                    "original_output": "run \"bucket_name\""
+                }),
+            ]
+        );
+        assert_eq!(
+            summary.test_summary.as_deref(),
+            Some("terraform test fail: 2 passed, 1 failed, 0 errored, 0 skipped")
+        );
+
+        let mut err = Vec::new();
+        write_summary(&opt, summary, &mut err).expect("Summary should be written");
+        assert_eq!(
+            String::from_utf8(err).expect("Summary should be UTF-8"),
+            "terraform test fail: 2 passed, 1 failed, 0 errored, 0 skipped\n"
+        );
+        let opt = Opt::from_iter(&["tfv2rd", "--input", "test", "--no-test-summary"]);
+        let summary = Summary {
+            test_summary: Some("terraform test pass: 1 passed".to_owned()),
+            ..Default::default()
+        };
+        let mut err = Vec::new();
+        write_summary(&opt, summary, &mut err).expect("Summary should be written");
+        assert!(err.is_empty(), "The test summary can be turned off");
+    }
 }
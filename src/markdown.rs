@@ -0,0 +1,148 @@
+// A Markdown report of the diagnostics, for a CI job summary or a merge request comment
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::codeowners;
+use crate::reviewdog as rd;
+
+// Writes a report with a table of totals, then the diagnostics grouped by file,
+// each with its detail and snippet of code collapsed under it.
+pub fn write(
+    out: &mut dyn Write,
+    all_diags: &[rd::Diagnostic],
+    overall_sev: rd::Severity,
+    source: rd::Source,
+) -> io::Result<()> {
+    let count = |severity| {
+        all_diags
+            .iter()
+            .filter(|d| d.severity.unwrap_or(rd::Severity::UnknownSeverity) == severity)
+            .count()
+    };
+    let (errors, warnings, infos, unknown) = (
+        count(rd::Severity::Error),
+        count(rd::Severity::Warning),
+        count(rd::Severity::Info),
+        count(rd::Severity::UnknownSeverity),
+    );
+
+    let icon = if all_diags.is_empty() {
+        "✅"
+    } else {
+        emoji(overall_sev)
+    };
+    writeln!(out, "## {} {}", icon, escape(source.name))?;
+    writeln!(out)?;
+    if all_diags.is_empty() {
+        writeln!(out, "No problems found.")?;
+    } else {
+        writeln!(out, "| Severity | Count |")?;
+        writeln!(out, "| --- | ---: |")?;
+        for (severity, n) in [
+            (rd::Severity::Error, errors),
+            (rd::Severity::Warning, warnings),
+            (rd::Severity::Info, infos),
+            (rd::Severity::UnknownSeverity, unknown),
+        ] {
+            if n > 0 || severity != rd::Severity::UnknownSeverity {
+                writeln!(out, "| {} {} | {} |", emoji(severity), name(severity), n)?;
+            }
+        }
+    }
+    if let Some(url) = source.url {
+        writeln!(out)?;
+        writeln!(out, "[Full output]({})", url)?;
+    }
+
+    let mut by_file: BTreeMap<&str, Vec<&rd::Diagnostic>> = BTreeMap::new();
+    for diag in all_diags {
+        by_file.entry(&diag.location.path).or_default().push(diag);
+    }
+    for (path, diags) in by_file {
+        writeln!(out)?;
+        writeln!(out, "### `{}`", path)?;
+        for diag in diags {
+            writeln!(out)?;
+            write_diagnostic(out, diag)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_diagnostic(out: &mut dyn Write, diag: &rd::Diagnostic) -> io::Result<()> {
+    let severity = diag.severity.unwrap_or(rd::Severity::UnknownSeverity);
+    writeln!(out, "<details>")?;
+    writeln!(
+        out,
+        "<summary>{} <b>{}</b> {}: {}</summary>",
+        emoji(severity),
+        name(severity),
+        lines(diag.location.range.as_ref()),
+        escape(&codeowners::owned_message(diag))
+    )?;
+    if let Some(code) = &diag.code {
+        writeln!(out)?;
+        match &code.url {
+            Some(url) => writeln!(out, "Rule: [`{}`]({})", code.value, url)?,
+            None => writeln!(out, "Rule: `{}`", code.value)?,
+        }
+    }
+    if let Some(detail) = &diag.original_output {
+        writeln!(out)?;
+        writeln!(out, "{}", escape(detail.trim_end()))?;
+    }
+    if let Some(snippet) = &diag.snippet {
+        let fence = fence(snippet);
+        writeln!(out)?;
+        writeln!(out, "{}hcl", fence)?;
+        writeln!(out, "{}", snippet.trim_end_matches('\n'))?;
+        writeln!(out, "{}", fence)?;
+    }
+    writeln!(out)?;
+    writeln!(out, "</details>")
+}
+
+// Describes where in the file a diagnostic is.
+fn lines(range: Option<&rd::Range>) -> String {
+    let start = range.and_then(|r| r.start.line);
+    let end = range
+        .and_then(|r| r.end.as_ref())
+        .and_then(|end| end.line)
+        .filter(|&end| Some(end) != start);
+    match (start, end) {
+        (Some(start), Some(end)) => format!("lines {}-{}", start, end),
+        (Some(start), None) => format!("line {}", start),
+        _ => "whole file".to_owned(),
+    }
+}
+
+// A code fence longer than any run of backticks in the code.
+fn fence(code: &str) -> String {
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+// Escapes text so Markdown renderers don't treat it as HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn emoji(severity: rd::Severity) -> &'static str {
+    match severity {
+        rd::Severity::Error => "❌",
+        rd::Severity::Warning => "⚠️",
+        rd::Severity::Info => "ℹ️",
+        rd::Severity::UnknownSeverity => "❔",
+    }
+}
+
+fn name(severity: rd::Severity) -> &'static str {
+    match severity {
+        rd::Severity::Error => "Error",
+        rd::Severity::Warning => "Warning",
+        rd::Severity::Info => "Info",
+        rd::Severity::UnknownSeverity => "Unknown",
+    }
+}
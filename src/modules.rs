@@ -0,0 +1,76 @@
+// Finding Terraform root modules and their files on disk, and running terraform validate in them
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use log::debug;
+
+use crate::engine::Engine;
+use crate::error::Error;
+
+// Runs terraform validate -json in a root module, returning its output.
+pub fn run_validate(engine: Engine, root: &Path) -> Result<String, Error> {
+    debug!("Running {} validate in {}", engine.binary(), root.display());
+    let output = Command::new(engine.binary())
+        .args(["validate", "-json", "-no-color"])
+        .current_dir(root)
+        .output()
+        .map_err(|source| Error::RunCommand {
+            command: format!("{} validate -json", engine.binary()),
+            source,
+        })?;
+    // terraform exits with an error when the configuration is invalid, but still
+    // outputs the diagnostics
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Whether a file is Terraform configuration or variable definitions.
+pub fn is_terraform_file(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    [".tf", ".tf.json", ".tfvars", ".tfvars.json"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+}
+
+// The root module a file belongs to: the nearest directory above it that has been
+// initialised with terraform init, or otherwise the file's own directory.
+pub fn root_module(file: &Path) -> PathBuf {
+    let dir = file
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    dir.ancestors()
+        .find(|d| d.join(".terraform").is_dir() || d.join(".terraform.lock.hcl").is_file())
+        .unwrap_or(dir)
+        .to_owned()
+}
+
+// The Terraform files in a module and the modules in directories below it, with
+// their modification times, skipping hidden directories like .terraform.
+pub fn module_files(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Can't read directory {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            match entry.metadata() {
+                Ok(meta) if meta.is_dir() && !hidden => dirs.push(path),
+                Ok(meta) if meta.is_file() && is_terraform_file(&path) => {
+                    files.push((path, meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+                }
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
@@ -0,0 +1,69 @@
+// Plain text in the style of compiler messages, one line per diagnostic, for
+// editors' quickfix lists, reviewdog -efm and grep
+use std::io::{self, Write};
+
+use crate::codeowners;
+use crate::reviewdog as rd;
+
+// Writes each diagnostic as "path:line:col: severity: message [code]", with its
+// detail indented on the lines after. With end_position, the end of the range is
+// given too, as "path:line:col-line:col".
+pub fn write(
+    out: &mut dyn Write,
+    all_diags: &[rd::Diagnostic],
+    end_position: bool,
+) -> io::Result<()> {
+    for diag in all_diags {
+        write!(out, "{}", diag.location.path)?;
+        if let Some(range) = &diag.location.range {
+            write_position(out, &range.start)?;
+            let end_line = range
+                .end
+                .as_ref()
+                .and_then(|end| end.line.map(|l| (l, end)));
+            if let (true, Some((line, end))) = (end_position, end_line) {
+                write!(out, "-{}", line)?;
+                if let Some(column) = end.column {
+                    write!(out, ":{}", column)?;
+                }
+            }
+        }
+        let severity = match diag.severity {
+            Some(rd::Severity::Error) => "error",
+            Some(rd::Severity::Warning) => "warning",
+            Some(rd::Severity::Info) => "info",
+            Some(rd::Severity::UnknownSeverity) | None => "note",
+        };
+        write!(
+            out,
+            ": {}: {}",
+            severity,
+            one_line(&codeowners::owned_message(diag))
+        )?;
+        if let Some(code) = &diag.code {
+            write!(out, " [{}]", code.value)?;
+        }
+        writeln!(out)?;
+        if let Some(detail) = &diag.original_output {
+            for line in detail.lines().filter(|line| !line.trim().is_empty()) {
+                writeln!(out, "  {}", line.trim_end())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_position(out: &mut dyn Write, pos: &rd::Position) -> io::Result<()> {
+    if let Some(line) = pos.line {
+        write!(out, ":{}", line)?;
+        if let Some(column) = pos.column {
+            write!(out, ":{}", column)?;
+        }
+    }
+    Ok(())
+}
+
+// Joins the lines of a message, so each diagnostic starts on a line of its own.
+fn one_line(message: &str) -> String {
+    message.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,332 @@
+// Encoding of the reviewdog diagnostic format as protocol buffers, per reviewdog's
+// rdf/proto/rdf/reviewdog.proto, written as length-delimited messages
+use std::io::{self, Write};
+
+use crate::reviewdog as rd;
+
+// Protobuf wire types.
+const VARINT: u32 = 0;
+const LEN: u32 = 2;
+
+// A message being encoded. As in proto3, fields with their default value are left out.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn tag(&mut self, field: u32, wire_type: u32) {
+        self.varint(u64::from(field << 3 | wire_type));
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.tag(field, VARINT);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.tag(field, LEN);
+        self.varint(value.len() as u64);
+        self.0.extend_from_slice(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        if !value.is_empty() {
+            self.bytes(field, value.as_bytes());
+        }
+    }
+
+    // Embedded messages are written even when empty, as they're present rather than null.
+    fn message(&mut self, field: u32, value: Message) {
+        self.bytes(field, &value.0);
+    }
+}
+
+// Writes the diagnostics as one length-delimited DiagnosticResult message.
+pub fn write_result(
+    out: &mut dyn Write,
+    all_diags: &[rd::Diagnostic],
+    overall_sev: rd::Severity,
+    source: rd::Source,
+) -> io::Result<()> {
+    let mut result = Message::default();
+    for diag in all_diags {
+        result.message(1, diagnostic(diag));
+    }
+    result.message(2, source_message(&source));
+    result.uint(3, severity(Some(overall_sev)));
+    write_delimited(out, result)
+}
+
+// Writes each diagnostic as a length-delimited Diagnostic message.
+pub fn write_diagnostics(out: &mut dyn Write, all_diags: &[rd::Diagnostic]) -> io::Result<()> {
+    for diag in all_diags {
+        write_delimited(out, diagnostic(diag))?;
+    }
+    Ok(())
+}
+
+// Writes a message preceded by its length as a varint, as protobuf libraries'
+// writeDelimitedTo and protodelim do.
+fn write_delimited(out: &mut dyn Write, message: Message) -> io::Result<()> {
+    let mut length = Message::default();
+    length.varint(message.0.len() as u64);
+    out.write_all(&length.0)?;
+    out.write_all(&message.0)
+}
+
+fn diagnostic(diag: &rd::Diagnostic) -> Message {
+    let mut m = Message::default();
+    m.string(1, &diag.message);
+    let mut location = Message::default();
+    location.string(2, &diag.location.path);
+    if let Some(range) = &diag.location.range {
+        location.message(3, range_message(range));
+    }
+    m.message(2, location);
+    m.uint(3, severity(diag.severity));
+    if let Some(source) = &diag.source {
+        m.message(4, source_message(source));
+    }
+    if let Some(code) = &diag.code {
+        let mut c = Message::default();
+        c.string(1, &code.value);
+        if let Some(url) = &code.url {
+            c.string(2, url);
+        }
+        m.message(5, c);
+    }
+    for suggestion in &diag.suggestions {
+        let mut s = Message::default();
+        s.message(1, range_message(&suggestion.range));
+        s.string(2, &suggestion.text);
+        m.message(6, s);
+    }
+    if let Some(original_output) = &diag.original_output {
+        m.string(7, original_output);
+    }
+    m
+}
+
+fn range_message(range: &rd::Range) -> Message {
+    let mut m = Message::default();
+    m.message(1, position(&range.start));
+    if let Some(end) = &range.end {
+        m.message(2, position(end));
+    }
+    m
+}
+
+fn position(pos: &rd::Position) -> Message {
+    let mut m = Message::default();
+    m.uint(1, pos.line.map_or(0, u64::from));
+    m.uint(2, pos.column.map_or(0, u64::from));
+    m
+}
+
+fn source_message(source: &rd::Source) -> Message {
+    let mut m = Message::default();
+    m.string(1, source.name);
+    if let Some(url) = source.url {
+        m.string(2, url);
+    }
+    m
+}
+
+fn severity(severity: Option<rd::Severity>) -> u64 {
+    match severity {
+        Some(rd::Severity::UnknownSeverity) | None => 0,
+        Some(rd::Severity::Error) => 1,
+        Some(rd::Severity::Warning) => 2,
+        Some(rd::Severity::Info) => 3,
+    }
+}
+
+// A decoder for the messages, written separately from the encoder from the field
+// definitions in reviewdog.proto, so tests can check that decoding the output gives
+// the same JSON as the reviewdog module serializes to.
+#[cfg(test)]
+pub mod decode {
+    use serde_json::{Map, Value};
+
+    #[derive(Clone, Copy)]
+    pub enum Kind {
+        String,
+        Int32,
+        Severity,
+        Message(&'static [Field]),
+    }
+
+    pub struct Field {
+        number: u64,
+        name: &'static str,
+        kind: Kind,
+        repeated: bool,
+    }
+
+    const fn field(number: u64, name: &'static str, kind: Kind) -> Field {
+        Field {
+            number,
+            name,
+            kind,
+            repeated: false,
+        }
+    }
+
+    const fn repeated(number: u64, name: &'static str, kind: Kind) -> Field {
+        Field {
+            number,
+            name,
+            kind,
+            repeated: true,
+        }
+    }
+
+    const SEVERITIES: [&str; 4] = ["UNKNOWN_SEVERITY", "ERROR", "WARNING", "INFO"];
+    const POSITION: &[Field] = &[
+        field(1, "line", Kind::Int32),
+        field(2, "column", Kind::Int32),
+    ];
+    const RANGE: &[Field] = &[
+        field(1, "start", Kind::Message(POSITION)),
+        field(2, "end", Kind::Message(POSITION)),
+    ];
+    const LOCATION: &[Field] = &[
+        field(2, "path", Kind::String),
+        field(3, "range", Kind::Message(RANGE)),
+    ];
+    const SOURCE: &[Field] = &[
+        field(1, "name", Kind::String),
+        field(2, "url", Kind::String),
+    ];
+    const CODE: &[Field] = &[
+        field(1, "value", Kind::String),
+        field(2, "url", Kind::String),
+    ];
+    const SUGGESTION: &[Field] = &[
+        field(1, "range", Kind::Message(RANGE)),
+        field(2, "text", Kind::String),
+    ];
+    pub const DIAGNOSTIC: &[Field] = &[
+        field(1, "message", Kind::String),
+        field(2, "location", Kind::Message(LOCATION)),
+        field(3, "severity", Kind::Severity),
+        field(4, "source", Kind::Message(SOURCE)),
+        field(5, "code", Kind::Message(CODE)),
+        repeated(6, "suggestions", Kind::Message(SUGGESTION)),
+        field(7, "original_output", Kind::String),
+    ];
+    pub const DIAGNOSTIC_RESULT: &[Field] = &[
+        repeated(1, "diagnostics", Kind::Message(DIAGNOSTIC)),
+        field(2, "source", Kind::Message(SOURCE)),
+        field(3, "severity", Kind::Severity),
+    ];
+
+    // Splits a stream of length-delimited messages.
+    pub fn split_delimited(mut input: &[u8]) -> Vec<&[u8]> {
+        let mut messages = Vec::new();
+        while !input.is_empty() {
+            let length = varint(&mut input) as usize;
+            let (message, rest) = input.split_at(length);
+            messages.push(message);
+            input = rest;
+        }
+        messages
+    }
+
+    // Decodes a message to JSON, panicking on anything unexpected. Fields left out
+    // have their defaults, as with any proto3 decoder.
+    pub fn message(mut input: &[u8], fields: &[Field]) -> Value {
+        let mut object = Map::new();
+        while !input.is_empty() {
+            let tag = varint(&mut input);
+            let field = fields
+                .iter()
+                .find(|f| f.number == tag >> 3)
+                .unwrap_or_else(|| panic!("Unknown field {}", tag >> 3));
+            let value = match (field.kind, tag & 7) {
+                (Kind::Int32, 0) => Value::from(varint(&mut input)),
+                (Kind::Severity, 0) => Value::from(SEVERITIES[varint(&mut input) as usize]),
+                (kind, 2) => {
+                    let length = varint(&mut input) as usize;
+                    let (bytes, rest) = input.split_at(length);
+                    input = rest;
+                    match kind {
+                        Kind::String => Value::from(
+                            String::from_utf8(bytes.to_vec()).expect("Strings are UTF-8"),
+                        ),
+                        Kind::Message(fields) => message(bytes, fields),
+                        _ => panic!("Field {} isn't length-delimited", field.name),
+                    }
+                }
+                (_, wire_type) => panic!("Unexpected wire type {} for {}", wire_type, field.name),
+            };
+            if field.repeated {
+                object
+                    .entry(field.name)
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                    .as_array_mut()
+                    .expect("Repeated fields are arrays")
+                    .push(value);
+            } else {
+                object.insert(field.name.to_owned(), value);
+            }
+        }
+        with_defaults(Value::Object(object), fields)
+    }
+
+    // Fills in the fields missing from a message with their proto3 defaults: empty
+    // strings and lists, 0 and UNKNOWN_SEVERITY. Embedded messages stay missing, as
+    // they're null rather than empty. Applied to the JSON the reviewdog module
+    // serializes to, which leaves out empty values, it can be compared with decoded messages.
+    pub fn with_defaults(value: Value, fields: &[Field]) -> Value {
+        let mut object = match value {
+            Value::Object(object) => object,
+            other => panic!("Expected a message, got {}", other),
+        };
+        for field in fields {
+            let default = match (field.repeated, field.kind) {
+                (true, _) => Some(Value::Array(Vec::new())),
+                (false, Kind::String) => Some(Value::from("")),
+                (false, Kind::Int32) => Some(Value::from(0u64)),
+                (false, Kind::Severity) => Some(Value::from(SEVERITIES[0])),
+                (false, Kind::Message(_)) => None,
+            };
+            if let Some(default) = default {
+                object.entry(field.name).or_insert(default);
+            }
+            if let Kind::Message(inner) = field.kind {
+                match object.get_mut(field.name) {
+                    Some(Value::Array(items)) => {
+                        for item in items {
+                            *item = with_defaults(item.take(), inner);
+                        }
+                    }
+                    Some(item @ Value::Object(_)) => *item = with_defaults(item.take(), inner),
+                    _ => {}
+                }
+            }
+        }
+        Value::Object(object)
+    }
+
+    fn varint(input: &mut &[u8]) -> u64 {
+        let mut value = 0;
+        for (i, &b) in input.iter().enumerate() {
+            value |= u64::from(b & 0x7f) << (7 * i);
+            if b < 0x80 {
+                *input = &input[i + 1..];
+                return value;
+            }
+        }
+        panic!("Truncated varint");
+    }
+}
@@ -1,6 +1,7 @@
 // Definitions of reviewdog diagnostic format
+use std::borrow::Cow;
+
 use serde::Serialize;
-use serde_json::value::RawValue;
 
 // Result of diagnostic tool such as a compiler or a linter.
 // It's intended to be used as top-level structured format which represents a
@@ -25,7 +26,7 @@ pub struct DiagnosticResult<'a> {
 #[derive(Debug, Serialize)]
 pub struct Diagnostic<'a> {
     // The diagnostic's message.
-    pub message: &'a RawValue,
+    pub message: Cow<'a, str>,
 
     // Location at which this diagnostic message applies.
     pub location: Location,
@@ -50,10 +51,30 @@ pub struct Diagnostic<'a> {
     // original_output represents the original output which corresponds to this
     // diagnostic.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub original_output: Option<&'a RawValue>,
+    pub original_output: Option<Cow<'a, str>>,
+
+    // Not part of the reviewdog format: the source code this diagnostic applies
+    // to, for output formats that show it.
+    #[serde(skip)]
+    pub snippet: Option<Cow<'a, str>>,
+
+    // Not part of the reviewdog format: Terraform's summary of the problem, which
+    // unlike the message doesn't have suggestions or other details added.
+    #[serde(skip)]
+    pub summary: Option<Cow<'a, str>>,
+
+    // Not part of the reviewdog format: the owners of the file, from CODEOWNERS,
+    // if it was read.
+    #[serde(skip)]
+    pub owners: Option<Vec<String>>,
+
+    // Not part of the reviewdog format: the root module the diagnostic came from,
+    // for the summary, if it's known.
+    #[serde(skip)]
+    pub root_module: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[allow(clippy::enum_variant_names)]
 pub enum Severity {
@@ -153,6 +174,19 @@ pub struct Position {
     pub column: Option<u32>,
 }
 
+impl Position {
+    // The position of a byte offset into the text of a file, or None if it's out of
+    // bounds or not on a character boundary.
+    pub fn at_byte(text: &str, byte: usize) -> Option<Position> {
+        let before = text.get(..byte)?;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Some(Position {
+            line: Some(before.matches('\n').count() as u32 + 1),
+            column: Some((byte - line_start) as u32 + 1),
+        })
+    }
+}
+
 // Suggestion represents a suggested text manipulation to resolve a diagnostic
 // problem.
 //
@@ -195,10 +229,10 @@ pub struct Suggestion<'a> {
 
     // A suggested text which replace the range.
     // For delete operations use an empty string.
-    pub text: &'a str,
+    pub text: Cow<'a, str>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Source<'a> {
     // A human-readable string describing the source of diagnostics, e.g.
     // 'typescript' or 'super lint'.
@@ -211,9 +245,9 @@ pub struct Source<'a> {
 #[derive(Debug, Serialize)]
 pub struct Code<'a> {
     // This rule's code/identifier.
-    pub value: &'a str,
+    pub value: Cow<'a, str>,
 
     // A URL to open with more information about this rule code.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub url: Option<&'a str>,
+    pub url: Option<Cow<'a, str>>,
 }
@@ -0,0 +1,133 @@
+// Conversion of findings from IaC security scanners like tfsec, Trivy and Checkov
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use log::warn;
+
+use crate::error::Error;
+use crate::reviewdog as rd;
+
+// A scanner result, in the common form all the scanners' output is converted to.
+pub struct Finding<'a> {
+    pub filename: Cow<'a, str>,
+    // Lines of the file the finding applies to, or 0 if it applies to the whole file.
+    pub start_line: u32,
+    pub end_line: u32,
+    pub message: Cow<'a, str>,
+    pub severity: rd::Severity,
+    pub rule_id: Cow<'a, str>,
+    pub url: Option<Cow<'a, str>>,
+    pub detail: Option<Cow<'a, str>>,
+}
+
+pub fn convert<'a>(
+    findings: impl IntoIterator<Item = Finding<'a>>,
+    path_converter: &dyn Fn(&str) -> Result<String, Error>,
+    skip_errors: bool,
+    source: rd::Source<'a>,
+) -> Result<Vec<rd::Diagnostic<'a>>, Error> {
+    let mut all_diags = Vec::new();
+    for finding in findings {
+        let path = match path_converter(&finding.filename) {
+            Ok(path) => path,
+            Err(e) if skip_errors => {
+                warn!(
+                    "The {} finding {} could not be converted and will be ignored: {}",
+                    source.name, finding.rule_id, e
+                );
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        all_diags.push(rd::Diagnostic {
+            message: finding.message,
+            location: rd::Location {
+                path,
+                range: line_range(finding.start_line, finding.end_line),
+            },
+            severity: Some(finding.severity),
+            source: Some(source),
+            code: Some(rd::Code {
+                value: finding.rule_id,
+                url: finding.url,
+            }),
+            suggestions: Vec::new(),
+            original_output: finding.detail,
+            snippet: None,
+            summary: None,
+            owners: None,
+            root_module: None,
+        });
+    }
+    Ok(all_diags)
+}
+
+// A linewise range covering whole lines, if the lines are known.
+fn line_range(start_line: u32, end_line: u32) -> Option<rd::Range> {
+    if start_line == 0 {
+        return None;
+    }
+    Some(rd::Range {
+        start: rd::Position {
+            line: Some(start_line),
+            column: None,
+        },
+        end: Some(rd::Position {
+            line: Some(end_line.max(start_line)),
+            column: None,
+        }),
+    })
+}
+
+// Mapping of the scanners' severity levels to Reviewdog severities.
+#[derive(Debug, Clone)]
+pub struct SeverityMap(HashMap<String, rd::Severity>);
+
+impl SeverityMap {
+    // Returns the Reviewdog severity for a scanner's severity level, ignoring case.
+    pub fn get(&self, level: &str) -> rd::Severity {
+        self.0
+            .get(&level.to_uppercase())
+            .copied()
+            .unwrap_or(rd::Severity::UnknownSeverity)
+    }
+}
+
+impl Default for SeverityMap {
+    fn default() -> Self {
+        SeverityMap(
+            [
+                ("CRITICAL", rd::Severity::Error),
+                ("HIGH", rd::Severity::Error),
+                ("MEDIUM", rd::Severity::Warning),
+                ("LOW", rd::Severity::Info),
+            ]
+            .iter()
+            .map(|(level, severity)| (level.to_string(), *severity))
+            .collect(),
+        )
+    }
+}
+
+impl FromStr for SeverityMap {
+    type Err = String;
+
+    // Parses a list like "HIGH=warning,LOW=info", overriding the default mapping.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = SeverityMap::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (level, severity) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected LEVEL=severity but got '{}'", entry))?;
+            let severity = match severity.trim().to_lowercase().as_str() {
+                "error" => rd::Severity::Error,
+                "warning" => rd::Severity::Warning,
+                "info" => rd::Severity::Info,
+                other => return Err(format!("Unknown severity '{}'", other)),
+            };
+            map.0.insert(level.trim().to_uppercase(), severity);
+        }
+        Ok(map)
+    }
+}
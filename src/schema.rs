@@ -0,0 +1,232 @@
+// Provider schemas from terraform providers schema -json, for suggesting the
+// names of arguments and blocks that were mistyped
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::Error;
+
+// Arguments every resource and data source can have, besides those in its schema.
+const META_ARGUMENTS: [&str; 4] = ["count", "depends_on", "for_each", "provider"];
+const RESOURCE_META_BLOCKS: [&str; 3] = ["connection", "lifecycle", "provisioner"];
+// Only names closer than this to the mistyped name are suggested, as in Terraform.
+const MAX_DISTANCE: usize = 3;
+
+#[derive(Deserialize, Debug)]
+pub struct ProviderSchemas {
+    provider_schemas: HashMap<String, ProviderSchema>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProviderSchema {
+    provider: Option<Schema>,
+    #[serde(default)]
+    resource_schemas: HashMap<String, Schema>,
+    #[serde(default)]
+    data_source_schemas: HashMap<String, Schema>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Schema {
+    block: Block,
+}
+
+#[derive(Deserialize, Debug)]
+struct Block {
+    #[serde(default)]
+    attributes: HashMap<String, Attribute>,
+    #[serde(default)]
+    block_types: HashMap<String, NestedBlock>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Attribute {
+    #[serde(default)]
+    required: bool,
+    #[serde(default)]
+    optional: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct NestedBlock {
+    block: Block,
+}
+
+// What kind of name was mistyped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    Argument,
+    BlockType,
+}
+
+// A block header in the source, from the top-level block down to the one a diagnostic is in.
+#[derive(Debug, PartialEq, Eq)]
+enum Nesting<'t> {
+    Block(&'t str),
+    // A dynamic block, which generates blocks of the type in its label.
+    Dynamic(&'t str),
+    // The content block of a dynamic block.
+    Content,
+    // Braces that aren't a block, e.g. an object expression.
+    Other,
+}
+
+// Reads the schemas from a file of terraform providers schema -json output.
+pub fn load(path: &Path) -> Result<ProviderSchemas, Error> {
+    let text = fs::read_to_string(path).map_err(|source| Error::ReadFile {
+        path: path.to_owned(),
+        source,
+    })?;
+    serde_json::from_str(&text).map_err(|source| Error::ParseFile {
+        path: path.to_owned(),
+        source,
+        expected: "terraform providers schema -json",
+    })
+}
+
+impl ProviderSchemas {
+    // Finds the valid name closest to a mistyped one, in the block of the source text
+    // that contains byte offset at. context is the header of the top-level block, as
+    // given in the diagnostic's snippet, e.g. resource "aws_instance" "web".
+    pub fn closest_name(
+        &self,
+        context: &str,
+        text: &str,
+        at: usize,
+        kind: NameKind,
+        name: &str,
+    ) -> Option<&str> {
+        let mut words = context.split_whitespace();
+        let (block_kind, type_name) = (words.next()?, words.next()?.trim_matches('"'));
+        let (schema, meta_arguments, meta_blocks): (_, &[&str], &[&str]) = match block_kind {
+            "resource" => (
+                self.find(|p| p.resource_schemas.get(type_name))?,
+                &META_ARGUMENTS,
+                &RESOURCE_META_BLOCKS,
+            ),
+            "data" => (
+                self.find(|p| p.data_source_schemas.get(type_name))?,
+                &META_ARGUMENTS,
+                &[],
+            ),
+            "provider" => (
+                self.provider_schemas
+                    .iter()
+                    .find(|(source, _)| source.rsplit('/').next() == Some(type_name))
+                    .and_then(|(_, p)| p.provider.as_ref())?,
+                &["alias"],
+                &[],
+            ),
+            _ => return None,
+        };
+
+        let nesting = nesting(text.get(..at)?);
+        let mut block = &schema.block;
+        let mut top_level = true;
+        for level in nesting.iter().skip(1) {
+            match level {
+                Nesting::Block(name) | Nesting::Dynamic(name) => {
+                    block = &block.block_types.get(*name)?.block;
+                    top_level = false;
+                }
+                Nesting::Content => {}
+                Nesting::Other => return None,
+            }
+        }
+
+        let candidates: Vec<&str> = match kind {
+            NameKind::Argument => block
+                .attributes
+                .iter()
+                .filter(|(_, attribute)| attribute.required || attribute.optional)
+                .map(|(name, _)| name.as_str())
+                .chain(meta_arguments.iter().copied().filter(|_| top_level))
+                .collect(),
+            NameKind::BlockType => block
+                .block_types
+                .keys()
+                .map(String::as_str)
+                .chain(meta_blocks.iter().copied().filter(|_| top_level))
+                .collect(),
+        };
+        candidates
+            .into_iter()
+            .map(|candidate| (distance(name, candidate), candidate))
+            .filter(|&(d, _)| d < MAX_DISTANCE)
+            .min()
+            .map(|(_, candidate)| candidate)
+    }
+
+    fn find<'s>(
+        &'s self,
+        f: impl Fn(&'s ProviderSchema) -> Option<&'s Schema>,
+    ) -> Option<&'s Schema> {
+        self.provider_schemas.values().find_map(f)
+    }
+}
+
+// Works out which blocks contain the end of the text, by matching up braces on
+// the lines before its last line. Comments and heredocs aren't understood.
+fn nesting(text: &str) -> Vec<Nesting<'_>> {
+    let before = &text[..text.rfind('\n').map_or(0, |i| i + 1)];
+    let mut stack = Vec::new();
+    for line in before.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+        let mut header = block_header(line, stack.last());
+        for c in line.chars() {
+            match c {
+                '{' => stack.push(header.take().unwrap_or(Nesting::Other)),
+                '}' => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    stack
+}
+
+// Parses a line opening a block, like `name "label" {`.
+fn block_header<'t>(line: &'t str, parent: Option<&Nesting>) -> Option<Nesting<'t>> {
+    let mut words = line.strip_suffix('{')?.split_whitespace();
+    let name = words.next()?;
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return None;
+    }
+    let labels: Vec<&str> = words.collect();
+    if !labels
+        .iter()
+        .all(|l| l.starts_with('"') && l.ends_with('"'))
+    {
+        return None;
+    }
+    Some(match (name, labels.first(), parent) {
+        ("dynamic", Some(label), _) => Nesting::Dynamic(label.trim_matches('"')),
+        ("content", None, Some(Nesting::Dynamic(_))) => Nesting::Content,
+        _ => Nesting::Block(name),
+    })
+}
+
+// The Levenshtein distance between two names.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,159 @@
+// SonarQube's generic issue import format, for sonar.externalIssuesReportPaths
+use std::collections::HashSet;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::reviewdog as rd;
+use crate::slug;
+
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    // Read by SonarQube 10.3 and later, which take the severity and type from here.
+    pub rules: Vec<Rule<'a>>,
+    pub issues: Vec<Issue<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule<'a> {
+    pub id: String,
+    pub name: &'a str,
+    pub description: &'a str,
+    pub engine_id: &'a str,
+    pub clean_code_attribute: &'static str,
+    #[serde(rename = "type")]
+    pub rule_type: &'static str,
+    pub severity: &'static str,
+    pub impacts: Vec<Impact>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Impact {
+    pub software_quality: &'static str,
+    pub severity: &'static str,
+}
+
+// An issue, which also has the engine, severity and type itself for SonarQube
+// versions before 10.3.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue<'a> {
+    pub engine_id: &'a str,
+    pub rule_id: String,
+    pub severity: &'static str,
+    #[serde(rename = "type")]
+    pub issue_type: &'static str,
+    pub primary_location: IssueLocation<'a>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueLocation<'a> {
+    pub message: &'a str,
+    pub file_path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_range: Option<TextRange>,
+}
+
+// Lines count from 1 and columns from 0.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextRange {
+    pub start_line: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_column: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_column: Option<u32>,
+}
+
+pub fn write(
+    out: &mut dyn Write,
+    all_diags: &[rd::Diagnostic],
+    source: rd::Source,
+) -> Result<(), Error> {
+    serde_json::to_writer(&mut *out, &report(all_diags, source))
+        .map_err(|e| Error::WriteOutput(e.into()))?;
+    out.write_all(b"\n").map_err(Error::WriteOutput)
+}
+
+pub fn report<'a>(all_diags: &'a [rd::Diagnostic], source: rd::Source<'a>) -> Report<'a> {
+    let mut rules = Vec::new();
+    let mut rule_ids = HashSet::new();
+    let mut issues = Vec::new();
+    for diag in all_diags {
+        let (name, rule_id) = rule(diag);
+        let (severity, issue_type, quality, impact) = match diag.severity {
+            Some(rd::Severity::Error) => ("CRITICAL", "BUG", "RELIABILITY", "HIGH"),
+            Some(rd::Severity::Warning) => ("MAJOR", "CODE_SMELL", "MAINTAINABILITY", "MEDIUM"),
+            Some(rd::Severity::Info) => ("INFO", "CODE_SMELL", "MAINTAINABILITY", "LOW"),
+            Some(rd::Severity::UnknownSeverity) | None => {
+                ("MINOR", "CODE_SMELL", "MAINTAINABILITY", "LOW")
+            }
+        };
+        let engine_id = diag.source.unwrap_or(source).name;
+        if rule_ids.insert(rule_id.clone()) {
+            rules.push(Rule {
+                id: rule_id.clone(),
+                name,
+                description: diag.original_output.as_deref().unwrap_or(name),
+                engine_id,
+                clean_code_attribute: if issue_type == "BUG" {
+                    "LOGICAL"
+                } else {
+                    "CONVENTIONAL"
+                },
+                rule_type: issue_type,
+                severity,
+                impacts: vec![Impact {
+                    software_quality: quality,
+                    severity: impact,
+                }],
+            });
+        }
+        issues.push(Issue {
+            engine_id,
+            rule_id,
+            severity,
+            issue_type,
+            primary_location: IssueLocation {
+                message: &diag.message,
+                file_path: &diag.location.path,
+                text_range: diag.location.range.as_ref().and_then(text_range),
+            },
+        });
+    }
+    Report { rules, issues }
+}
+
+// The name and id of the rule a diagnostic breaks: its rule code, or for Terraform
+// diagnostics, which don't have one, its summary. The message isn't used, as it can
+// have values, suggestions or owners added that would make a rule per diagnostic.
+fn rule<'a>(diag: &'a rd::Diagnostic) -> (&'a str, String) {
+    let code = diag.code.as_ref().map(|code| code.value.as_ref());
+    let name = diag.summary.as_deref().or(code).unwrap_or(&diag.message);
+    let id = match code {
+        Some(code) => code.to_owned(),
+        None => slug(name),
+    };
+    (name, id)
+}
+
+// Converts a range to SonarQube's, which counts columns from 0. The columns are
+// Reviewdog's byte columns, so are only exact for ASCII lines.
+fn text_range(range: &rd::Range) -> Option<TextRange> {
+    let column = |pos: &rd::Position| pos.column.map(|c| c.saturating_sub(1));
+    let end = range.end.as_ref();
+    Some(TextRange {
+        start_line: range.start.line?,
+        end_line: end.and_then(|end| end.line),
+        start_column: column(&range.start).filter(|_| end.is_some_and(|e| e.column.is_some())),
+        end_column: end
+            .and_then(column)
+            .filter(|_| range.start.column.is_some()),
+    })
+}
@@ -0,0 +1,87 @@
+// Access to the Terraform source files that diagnostics refer to
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use log::debug;
+
+use crate::terraform as tf;
+
+pub struct SourceFiles {
+    workdir: Option<PathBuf>,
+    cache: RefCell<HashMap<String, Option<Rc<str>>>>,
+}
+
+impl SourceFiles {
+    // Filenames are resolved relative to workdir, or the current directory if it's not set.
+    pub fn new(workdir: Option<PathBuf>) -> Self {
+        SourceFiles {
+            workdir,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Returns the contents of a file named in Terraform output, or None if it can't be read.
+    pub fn get(&self, filename: &str) -> Option<Rc<str>> {
+        self.cache
+            .borrow_mut()
+            .entry(filename.to_owned())
+            .or_insert_with(|| {
+                let path = match &self.workdir {
+                    Some(workdir) => workdir.join(filename),
+                    None => PathBuf::from(filename),
+                };
+                fs::read_to_string(&path)
+                    .map_err(|e| debug!("Can't read source file {}: {}", path.display(), e))
+                    .ok()
+                    .map(Rc::from)
+            })
+            .clone()
+    }
+}
+
+// Terraform counts columns in characters, but Reviewdog counts them in UTF-8 bytes.
+// This works out the byte column of a position from its byte offset in the source
+// file, or from the snippet of source code if the file isn't available, falling
+// back to the character column if neither can be used.
+pub fn byte_column(
+    pos: &tf::SourcePosition,
+    file: Option<&str>,
+    snippet: Option<&tf::Snippet>,
+) -> u32 {
+    file.and_then(|text| byte_column_in_file(pos, text))
+        .or_else(|| snippet.and_then(|snippet| byte_column_in_snippet(pos, snippet)))
+        .unwrap_or(pos.column)
+}
+
+// Checks that a position's byte offset falls on its line in the text.
+pub fn position_matches(text: &str, pos: &tf::SourcePosition) -> bool {
+    text.get(..pos.byte as usize)
+        .is_some_and(|before| before.matches('\n').count() + 1 == pos.line as usize)
+}
+
+fn byte_column_in_file(pos: &tf::SourcePosition, text: &str) -> Option<u32> {
+    // Check the file matches the one Terraform read before trusting the offset
+    if !position_matches(text, pos) {
+        return None;
+    }
+    let before = &text[..pos.byte as usize];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Some((before.len() - line_start) as u32 + 1)
+}
+
+fn byte_column_in_snippet(pos: &tf::SourcePosition, snippet: &tf::Snippet) -> Option<u32> {
+    let line = snippet
+        .code
+        .lines()
+        .nth(pos.line.checked_sub(snippet.start_line)? as usize)?;
+    let chars_before = pos.column.checked_sub(1)? as usize;
+    match line.char_indices().nth(chars_before) {
+        Some((i, _)) => Some(i as u32 + 1),
+        // The position can be just past the last character of the line
+        None if line.chars().count() == chars_before => Some(line.len() as u32 + 1),
+        None => None,
+    }
+}
@@ -0,0 +1,123 @@
+// Suggested fixes for Terraform diagnostics that can be fixed mechanically
+use std::borrow::Cow;
+
+use log::debug;
+
+use crate::reviewdog as rd;
+use crate::schema::{NameKind, ProviderSchemas};
+use crate::sources;
+use crate::terraform as tf;
+
+// The kinds of diagnostic that can be fixed.
+enum Fix<'s> {
+    UnquoteType,
+    UnwrapInterpolation,
+    // Correct a mistyped name to one from the provider schema.
+    Rename(&'s ProviderSchemas, NameKind),
+}
+
+// Builds the suggestions for a diagnostic, replacing the text it highlights. The
+// highlighted text can span several lines, so it's only suggested if Terraform's
+// snippet and the source file agree on what it is, as reviewdog applies the
+// suggestion to the file and would otherwise corrupt it. Mistyped names are only
+// corrected with a provider schema.
+pub fn suggestions(
+    diag: &tf::Diagnostic,
+    file: Option<&str>,
+    schema: Option<&ProviderSchemas>,
+) -> Vec<rd::Suggestion<'static>> {
+    let fix = match (diag.summary.as_ref(), schema) {
+        ("Invalid quoted type constraints", _) => Fix::UnquoteType,
+        ("Interpolation-only expressions are deprecated", _) => Fix::UnwrapInterpolation,
+        ("Unsupported argument", Some(schema)) => Fix::Rename(schema, NameKind::Argument),
+        ("Unsupported block type", Some(schema)) => Fix::Rename(schema, NameKind::BlockType),
+        _ => return Vec::new(),
+    };
+    let (range, snippet, text) = match (&diag.range, &diag.snippet, file) {
+        (Some(range), Some(snippet), Some(text)) => (range, snippet, text),
+        _ => return Vec::new(),
+    };
+    let (start, end) = match highlighted(range, snippet, text) {
+        Some(bytes) => bytes,
+        None => {
+            debug!(
+                "Not suggesting a fix for {} in {} as the snippet doesn't match the file",
+                diag.summary, range.filename
+            );
+            return Vec::new();
+        }
+    };
+    let replacement = match fix {
+        Fix::UnquoteType => unquote_type(&text[start..end]),
+        Fix::UnwrapInterpolation => unwrap_interpolation(&text[start..end]),
+        Fix::Rename(schema, kind) => snippet.context.as_deref().and_then(|context| {
+            schema
+                .closest_name(context, text, start, kind, &text[start..end])
+                .map(str::to_owned)
+        }),
+    };
+    replacement
+        .and_then(|replacement| {
+            Some(rd::Suggestion {
+                range: rd::Range {
+                    start: rd::Position::at_byte(text, start)?,
+                    end: Some(rd::Position::at_byte(text, end)?),
+                },
+                text: Cow::Owned(replacement),
+            })
+        })
+        .into_iter()
+        .collect()
+}
+
+// The message for a diagnostic, which names the suggested replacement for a
+// mistyped name as Terraform's own "Did you mean" hint would.
+pub fn message<'a>(diag: &'a tf::Diagnostic, suggestions: &[rd::Suggestion]) -> Cow<'a, str> {
+    match (diag.summary.as_ref(), suggestions.first()) {
+        ("Unsupported argument" | "Unsupported block type", Some(suggestion)) => Cow::Owned(
+            format!("{}, did you mean \"{}\"?", diag.summary, suggestion.text),
+        ),
+        _ => Cow::Borrowed(&diag.summary),
+    }
+}
+
+// Finds the byte range of the file a diagnostic highlights, checking the snippet's
+// start_line and highlighted code match the file at that range.
+fn highlighted(range: &tf::Range, snippet: &tf::Snippet, text: &str) -> Option<(usize, usize)> {
+    let (start, end) = (range.start.as_ref()?, range.end.as_ref()?);
+    if !sources::position_matches(text, start) || !sources::position_matches(text, end) {
+        return None;
+    }
+    let (start_byte, end_byte) = (start.byte as usize, end.byte as usize);
+    let in_file = text.get(start_byte..end_byte)?;
+    let before = snippet
+        .code
+        .get(..snippet.highlight_start_offset as usize)?;
+    let in_snippet = snippet
+        .code
+        .get(snippet.highlight_start_offset as usize..snippet.highlight_end_offset as usize)?;
+    let first_line = snippet.start_line + before.matches('\n').count() as u32;
+    if first_line != start.line || in_snippet != in_file {
+        return None;
+    }
+    Some((start_byte, end_byte))
+}
+
+// "string" becomes string, and the bare "list" and "map" get the string element
+// type Terraform 0.11 assumed.
+fn unquote_type(quoted: &str) -> Option<String> {
+    let type_name = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    Some(match type_name {
+        "list" | "map" => format!("{}(string)", type_name),
+        _ if type_name.contains('"') => return None,
+        _ => type_name.to_owned(),
+    })
+}
+
+// "${expression}" becomes expression, keeping any line breaks within it.
+fn unwrap_interpolation(template: &str) -> Option<String> {
+    let inner = template.strip_prefix("\"${")?.strip_suffix("}\"")?;
+    let inner = inner.strip_prefix('~').unwrap_or(inner);
+    let inner = inner.strip_suffix('~').unwrap_or(inner);
+    Some(inner.trim().to_owned())
+}
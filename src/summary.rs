@@ -0,0 +1,198 @@
+// Statistics about a conversion, for dashboards and for noticing diagnostics that were left out
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use log::warn;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::modules;
+use crate::reviewdog as rd;
+use crate::terraform as tf;
+
+// The owner diagnostics for files without any owners are counted under.
+const UNOWNED: &str = "(unowned)";
+
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    // Number of diagnostics in the output.
+    pub emitted: u32,
+    pub by_severity: BTreeMap<&'static str, u32>,
+    // Only diagnostics with a rule code are counted here.
+    pub by_rule: BTreeMap<String, u32>,
+    pub by_file: BTreeMap<String, u32>,
+    // Keyed by the root module each diagnostic came from, with paths like those of the files.
+    pub by_root_module: BTreeMap<String, u32>,
+    // Keyed by each owner from CODEOWNERS, when it's read, or "(unowned)".
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub by_owner: BTreeMap<String, u32>,
+    // Diagnostics left out of the output as they have no source file location.
+    pub skipped_no_range: u32,
+    // Diagnostics left out of the output as they couldn't be converted, with --skip-errors.
+    pub skipped_errors: u32,
+    // Diagnostics left out of the output as their files aren't owned by anyone given with --owner.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub skipped_other_owners: u32,
+    // The counts terraform validate gave, when the input has them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reported: Option<Reported>,
+    // The outcome of the whole run terraform test reported, for test input.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_summary: Option<String>,
+    // Explanations of any differences between the reported and emitted counts.
+    pub discrepancies: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Reported {
+    pub valid: bool,
+    pub error_count: u32,
+    pub warning_count: u32,
+}
+
+impl Summary {
+    // Counts a diagnostic that was output.
+    pub fn record(&mut self, diag: &rd::Diagnostic) {
+        self.emitted += 1;
+        *self
+            .by_severity
+            .entry(severity_name(diag.severity))
+            .or_default() += 1;
+        if let Some(code) = &diag.code {
+            *self.by_rule.entry(code.value.to_string()).or_default() += 1;
+        }
+        *self.by_file.entry(diag.location.path.clone()).or_default() += 1;
+        let root_module = diag.root_module.as_deref().unwrap_or(".");
+        *self
+            .by_root_module
+            .entry(root_module.to_owned())
+            .or_default() += 1;
+        match diag.owners.as_deref() {
+            Some([]) => *self.by_owner.entry(UNOWNED.to_owned()).or_default() += 1,
+            Some(owners) => {
+                for owner in owners {
+                    *self.by_owner.entry(owner.clone()).or_default() += 1;
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Counts diagnostics that were left out of the output.
+    pub fn skipped(&mut self, no_range: usize, unconverted: usize) {
+        self.skipped_no_range += no_range as u32;
+        self.skipped_errors += unconverted as u32;
+    }
+
+    // Counts a diagnostic left out of the output by --owner.
+    pub fn skipped_owner(&mut self) {
+        self.skipped_other_owners += 1;
+    }
+
+    // Adds the counts from a terraform validate result, which for several results
+    // (e.g. from terragrunt run-all) are added up.
+    pub fn reported(&mut self, result: &tf::ValidateResult) {
+        let reported = self.reported.get_or_insert(Reported {
+            valid: true,
+            ..Reported::default()
+        });
+        reported.valid &= result.valid;
+        reported.error_count += result.error_count;
+        reported.warning_count += result.warning_count;
+    }
+
+    // Compares the counts terraform reported with the diagnostics that were output,
+    // explaining any differences.
+    pub fn cross_check(&mut self) {
+        let reported = match &self.reported {
+            Some(reported) => reported,
+            None => return,
+        };
+        let mut discrepancies = Vec::new();
+        for (name, count) in [
+            ("error", reported.error_count),
+            ("warning", reported.warning_count),
+        ] {
+            let emitted = self.by_severity.get(name.to_uppercase().as_str());
+            let emitted = emitted.copied().unwrap_or(0);
+            if emitted != count {
+                discrepancies.push(format!(
+                    "terraform reported {} {}(s) but {} were output",
+                    count, name, emitted
+                ));
+            }
+        }
+        if !reported.valid && reported.error_count == 0 {
+            discrepancies.push(
+                "terraform reported the configuration as invalid without any errors".to_owned(),
+            );
+        } else if reported.valid && reported.error_count > 0 {
+            discrepancies
+                .push("terraform reported the configuration as valid despite errors".to_owned());
+        }
+        if !discrepancies.is_empty() && self.skipped_no_range + self.skipped_errors > 0 {
+            discrepancies.push(format!(
+                "{} diagnostic(s) had no source file location and {} couldn't be converted",
+                self.skipped_no_range, self.skipped_errors
+            ));
+        }
+        if !discrepancies.is_empty() && self.skipped_other_owners > 0 {
+            discrepancies.push(format!(
+                "{} diagnostic(s) were for files with other owners",
+                self.skipped_other_owners
+            ));
+        }
+        for discrepancy in &discrepancies {
+            warn!("{}", discrepancy);
+        }
+        self.discrepancies = discrepancies;
+    }
+
+    // Writes the summary as indented JSON to a file, or to stderr for "-" as stdout has the diagnostics.
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let mut out: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stderr())
+        } else {
+            Box::new(File::create(path).map_err(Error::WriteOutput)?)
+        };
+        serde_json::to_writer_pretty(&mut out, self).map_err(|e| Error::WriteOutput(e.into()))?;
+        out.write_all(b"\n").map_err(Error::WriteOutput)
+    }
+}
+
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+// The root module terraform ran in, from its directory as given in the input, converted
+// like the paths of files.
+pub fn root_dir(path_converter: &dyn Fn(&str) -> Result<String, Error>, dir: &str) -> String {
+    match path_converter(dir) {
+        Ok(root) if !root.is_empty() => root,
+        _ => ".".to_owned(),
+    }
+}
+
+// The root module of a file from a scan of a whole tree of modules, found on disk. The
+// path is relative to base if given, or otherwise to the current directory.
+pub fn root_on_disk(path: &str, base: Option<&Path>) -> String {
+    let base = base.unwrap_or_else(|| Path::new(""));
+    let root = modules::root_module(&base.join(path));
+    let root = root.strip_prefix(base).unwrap_or(&root).to_string_lossy();
+    if root.is_empty() {
+        ".".to_owned()
+    } else {
+        root.into_owned()
+    }
+}
+
+pub fn severity_name(severity: Option<rd::Severity>) -> &'static str {
+    match severity {
+        Some(rd::Severity::Error) => "ERROR",
+        Some(rd::Severity::Warning) => "WARNING",
+        Some(rd::Severity::Info) => "INFO",
+        Some(rd::Severity::UnknownSeverity) | None => "UNKNOWN_SEVERITY",
+    }
+}
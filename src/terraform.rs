@@ -13,14 +13,75 @@ pub struct ValidateResult<'a> {
     pub diagnostics: Vec<Diagnostic<'a>>,
 }
 
+// One line of the machine-readable UI output of commands like terraform plan -json.
+#[derive(Debug, Deserialize)]
+pub struct StreamMessage<'a> {
+    // Not used, but required so other JSON isn't taken for a message.
+    #[allow(dead_code)]
+    #[serde(rename = "@level", borrow)]
+    pub level: Cow<'a, str>,
+    #[serde(rename = "@message", borrow)]
+    pub message: Cow<'a, str>,
+    #[serde(rename = "@module", borrow, default)]
+    pub module: Option<Cow<'a, str>>,
+    #[serde(rename = "type", borrow)]
+    pub message_type: Cow<'a, str>,
+    #[serde(borrow)]
+    pub diagnostic: Option<Diagnostic<'a>>,
+    // The version of OpenTofu, in its version message.
+    #[serde(borrow, default)]
+    pub tofu: Option<Cow<'a, str>>,
+    // The following are only present in terraform test -json output.
+    #[serde(rename = "@testrun", borrow, default)]
+    pub test_run_name: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    pub test_file: Option<TestFile<'a>>,
+    #[serde(borrow, default)]
+    pub test_run: Option<TestRun<'a>>,
+    #[serde(borrow, default)]
+    pub test_summary: Option<TestSummary<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestFile<'a> {
+    #[serde(borrow)]
+    pub path: Cow<'a, str>,
+    #[serde(borrow)]
+    pub progress: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub status: Option<Cow<'a, str>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestRun<'a> {
+    #[serde(borrow)]
+    pub path: Cow<'a, str>,
+    #[serde(borrow)]
+    pub run: Cow<'a, str>,
+    #[serde(borrow)]
+    pub progress: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub status: Option<Cow<'a, str>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestSummary<'a> {
+    #[serde(borrow)]
+    pub status: Cow<'a, str>,
+    pub passed: u32,
+    pub failed: u32,
+    pub errored: u32,
+    pub skipped: u32,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Diagnostic<'a> {
     #[serde(borrow)]
     pub severity: Cow<'a, str>,
     #[serde(borrow)]
-    pub summary: &'a RawValue,
+    pub summary: Cow<'a, str>,
     #[serde(borrow)]
-    pub detail: Option<&'a RawValue>,
+    pub detail: Option<Cow<'a, str>>,
     pub range: Option<Range<'a>>,
     pub snippet: Option<Snippet<'a>>,
 }
@@ -33,7 +94,7 @@ pub struct Range<'a> {
     pub end: Option<SourcePosition>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct SourcePosition {
     pub byte: u32,
     pub line: u32,
@@ -42,19 +103,11 @@ pub struct SourcePosition {
 
 #[derive(Debug, Deserialize)]
 pub struct Snippet<'a> {
-    pub context: Option<&'a RawValue>,
     #[serde(borrow)]
-    pub code: &'a RawValue,
+    pub context: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub code: Cow<'a, str>,
     pub start_line: u32,
     pub highlight_start_offset: u32,
     pub highlight_end_offset: u32,
-    pub values: Vec<Expression<'a>>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct Expression<'a> {
-    #[serde(borrow)]
-    pub traversal: &'a RawValue,
-    #[serde(borrow)]
-    pub statement: &'a RawValue,
 }
@@ -0,0 +1,220 @@
+// Mapping Terragrunt's copies of module sources back to the original files, and
+// splitting terragrunt run-all output into the output of each unit
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use log::{debug, warn};
+
+// Directory Terragrunt copies module sources into, inside each unit's directory.
+const CACHE_DIR: &str = ".terragrunt-cache";
+const CONFIG_FILE: &str = "terragrunt.hcl";
+
+// Where a unit's module comes from, per the terraform.source in its terragrunt.hcl.
+// Terragrunt copies the whole directory before a "//" in the source into the cache,
+// and runs terraform in the subdirectory after it.
+pub struct ModuleSource {
+    // The copied directory relative to the unit, or None if it isn't a local path.
+    local_root: Option<PathBuf>,
+    subdir: PathBuf,
+}
+
+// The module source of each unit whose terragrunt.hcl has been read, or None if it
+// has none that can be understood, so it's read once rather than for every file.
+pub type Sources = RefCell<HashMap<PathBuf, Option<ModuleSource>>>;
+
+// The part of terragrunt run-all output that came from one unit.
+pub struct UnitOutput {
+    // The unit's directory from the line prefixes, if the output has them.
+    pub dir: Option<String>,
+    pub text: String,
+}
+
+// Maps a path inside a unit's .terragrunt-cache/<hash>/<hash>/ directory back to
+// the file it was copied from, or returns None if it isn't in a cache or its origin
+// can't be worked out. Relative paths are resolved against the current directory.
+pub fn uncache(path: &Path, sources: &Sources) -> Option<PathBuf> {
+    let components: Vec<Component> = path.components().collect();
+    let cache = components.iter().position(|c| c.as_os_str() == CACHE_DIR)?;
+    let unit: PathBuf = components[..cache].iter().collect();
+    // Skip the two hash directories
+    let in_copy: PathBuf = components.get(cache + 3..)?.iter().collect();
+    if in_copy.as_os_str().is_empty() {
+        return None;
+    }
+    let mut sources = sources.borrow_mut();
+    let source = sources
+        .entry(unit.clone())
+        .or_insert_with(|| read_source(Path::new(""), &unit));
+    let original = source
+        .as_ref()
+        .and_then(|source| original(Path::new(""), &unit, source, &normalize(&in_copy)));
+    if original.is_none() {
+        warn!(
+            "{} is in the Terragrunt cache but the file it was copied from can't be found",
+            path.display()
+        );
+    }
+    original
+}
+
+// Maps a filename terraform reported while running in a unit's module back to the
+// original file, relative to the directory terragrunt run-all was run in. base is
+// that directory, for reading the unit's configuration.
+pub fn unit_file(base: &Path, unit: &str, filename: &str, sources: &Sources) -> String {
+    let unit = Path::new(unit);
+    let mut sources = sources.borrow_mut();
+    let source = sources
+        .entry(base.join(unit))
+        .or_insert_with(|| read_source(base, unit));
+    let path = match source {
+        Some(source) => {
+            let in_copy = normalize(&source.subdir.join(filename));
+            original(base, unit, source, &in_copy).unwrap_or_else(|| unit.join(filename))
+        }
+        // Without a source terraform runs in the unit's own directory
+        None => unit.join(filename),
+    };
+    normalize(&path).to_string_lossy().into_owned()
+}
+
+// Finds the original of a file at in_copy within the copy of a unit's module source.
+fn original(base: &Path, unit: &Path, source: &ModuleSource, in_copy: &Path) -> Option<PathBuf> {
+    // The unit's own files are copied into the module directory, over the module's files
+    if let Ok(in_unit) = in_copy.strip_prefix(&source.subdir) {
+        if base.join(unit).join(in_unit).is_file() {
+            return Some(normalize(&unit.join(in_unit)));
+        }
+    }
+    let root = source.local_root.as_ref()?;
+    Some(normalize(&unit.join(root).join(in_copy)))
+}
+
+fn read_source(base: &Path, unit: &Path) -> Option<ModuleSource> {
+    let config_path = base.join(unit).join(CONFIG_FILE);
+    let config = fs::read_to_string(&config_path)
+        .map_err(|e| debug!("Can't read {}: {}", config_path.display(), e))
+        .ok()?;
+    let source = source_attribute(&config)?;
+    let source = source.replace("${get_terragrunt_dir()}", ".");
+    if source.contains("${") {
+        debug!(
+            "The terraform source in {} uses functions that aren't supported: {}",
+            config_path.display(),
+            source
+        );
+        return None;
+    }
+    Some(parse_source(&source))
+}
+
+// Splits a module source at "//", ignoring the one in a URL scheme like https://.
+fn parse_source(source: &str) -> ModuleSource {
+    let source = source.split('?').next().unwrap_or(source);
+    let local = source.starts_with("./") || source.starts_with("../") || source.starts_with('/');
+    let search_from = source.find("://").map_or(0, |i| i + 3);
+    let (root, subdir) = match source[search_from..].find("//") {
+        Some(i) => (&source[..search_from + i], &source[search_from + i + 2..]),
+        None => (source, ""),
+    };
+    ModuleSource {
+        local_root: if local {
+            Some(PathBuf::from(root))
+        } else {
+            None
+        },
+        subdir: normalize(Path::new(subdir)),
+    }
+}
+
+// Finds the source attribute of the terraform block in a terragrunt.hcl file.
+// Only a plain string is understood, not HCL expressions in general.
+fn source_attribute(config: &str) -> Option<String> {
+    let mut depth = 0;
+    let mut in_terraform = false;
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+        if depth == 0 {
+            in_terraform = line
+                .strip_prefix("terraform")
+                .is_some_and(|rest| rest.trim_start().starts_with('{'));
+        } else if depth == 1 && in_terraform {
+            let value = line
+                .strip_prefix("source")
+                .and_then(|rest| rest.trim_start().strip_prefix('='))
+                .and_then(|rest| rest.trim_start().strip_prefix('"'))
+                .and_then(|rest| rest.split('"').next());
+            if let Some(value) = value {
+                return Some(value.to_owned());
+            }
+        }
+        depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+    }
+    None
+}
+
+// Resolves "." and ".." components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Splits terragrunt run-all output into the output of each unit. Terragrunt
+// prefixes each line with the unit's directory, e.g. "[live/prod/vpc] {", or with
+// its newer log format "12:00:00.000 STDOUT [live/prod/vpc] terraform: {". Lines
+// without a prefix belong to the unit before them.
+pub fn split_run_all(input: &str) -> Vec<UnitOutput> {
+    let mut units: Vec<UnitOutput> = Vec::new();
+    let mut index: HashMap<Option<String>, usize> = HashMap::new();
+    let mut current = None;
+    for line in input.lines() {
+        let text = match split_prefix(line) {
+            Some((dir, rest)) => {
+                current = Some(dir.to_owned());
+                rest
+            }
+            None => line,
+        };
+        let i = *index.entry(current.clone()).or_insert_with(|| {
+            units.push(UnitOutput {
+                dir: current.clone(),
+                text: String::new(),
+            });
+            units.len() - 1
+        });
+        units[i].text.push_str(text);
+        units[i].text.push('\n');
+    }
+    units
+}
+
+fn split_prefix(line: &str) -> Option<(&str, &str)> {
+    let line = match line.find(" STDOUT [") {
+        Some(i) => &line[i + " STDOUT ".len()..],
+        None => line,
+    };
+    let (dir, rest) = line.strip_prefix('[')?.split_once("] ")?;
+    if dir.is_empty() || dir.contains(|c: char| c == '"' || c.is_whitespace()) {
+        return None;
+    }
+    let rest = rest
+        .strip_prefix("terraform: ")
+        .or_else(|| rest.strip_prefix("tofu: "))
+        .unwrap_or(rest);
+    Some((dir, rest))
+}
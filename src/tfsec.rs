@@ -0,0 +1,85 @@
+// tfsec JSON output definitions
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::scanner::{Finding, SeverityMap};
+
+#[derive(Debug, Deserialize)]
+pub struct Report<'a> {
+    // tfsec writes null rather than an empty list when there are no results, but always
+    // writes the key, which tells its output apart from other JSON.
+    #[serde(borrow, deserialize_with = "nullable")]
+    pub results: Option<Vec<ScanResult<'a>>>,
+}
+
+// Deserializes a field that may be null but must be present, unlike serde's default
+// for an Option.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanResult<'a> {
+    #[serde(borrow)]
+    pub rule_id: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub long_id: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub severity: Cow<'a, str>,
+    #[serde(borrow)]
+    pub description: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub resolution: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    pub links: Vec<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub location: Location<'a>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Location<'a> {
+    #[serde(borrow)]
+    pub filename: Cow<'a, str>,
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+impl<'a> Report<'a> {
+    pub fn findings(self, severities: &SeverityMap) -> Vec<Finding<'a>> {
+        self.results
+            .unwrap_or_default()
+            .into_iter()
+            .map(|result| {
+                let ScanResult {
+                    rule_id,
+                    long_id,
+                    severity,
+                    description,
+                    resolution,
+                    links,
+                    location,
+                } = result;
+                Finding {
+                    filename: location.filename,
+                    start_line: location.start_line,
+                    end_line: location.end_line,
+                    message: description,
+                    severity: severities.get(&severity),
+                    rule_id,
+                    url: links.into_iter().next(),
+                    detail: match (long_id, resolution) {
+                        (Some(long_id), Some(resolution)) => {
+                            Some(Cow::Owned(format!("{}: {}", long_id, resolution)))
+                        }
+                        (_, resolution) => resolution,
+                    },
+                }
+            })
+            .collect()
+    }
+}
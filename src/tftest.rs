@@ -0,0 +1,152 @@
+// Summaries of terraform test -json results
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::reviewdog as rd;
+use crate::terraform as tf;
+
+// Collects the results of each test run, to summarise them when their test file completes.
+#[derive(Debug, Default)]
+pub struct TestResults {
+    // The names of the completed runs in each test file, with whether they passed.
+    runs: HashMap<String, Vec<(String, bool)>>,
+    // The counts of the whole test run, once terraform test has reported them.
+    totals: Option<Totals>,
+}
+
+#[derive(Debug)]
+struct Totals {
+    status: String,
+    passed: u32,
+    failed: u32,
+    errored: u32,
+    skipped: u32,
+}
+
+// The outcome of a test file that didn't pass.
+#[derive(Debug)]
+pub struct FileResult {
+    pub path: String,
+    pub status: String,
+    pub failed_runs: Vec<String>,
+    pub total_runs: usize,
+}
+
+impl TestResults {
+    // Records a message, returning the result of a test file if it has just failed.
+    pub fn observe(&mut self, message: &tf::StreamMessage) -> Option<FileResult> {
+        if let Some(run) = &message.test_run {
+            if run.progress == "complete" {
+                let passed = matches!(run.status.as_deref(), Some("pass") | Some("skip"));
+                self.runs
+                    .entry(run.path.to_string())
+                    .or_default()
+                    .push((run.run.to_string(), passed));
+            }
+        }
+        if let Some(summary) = &message.test_summary {
+            self.totals = Some(Totals {
+                status: summary.status.to_string(),
+                passed: summary.passed,
+                failed: summary.failed,
+                errored: summary.errored,
+                skipped: summary.skipped,
+            });
+        }
+        let file = message.test_file.as_ref()?;
+        if file.progress != "complete" {
+            return None;
+        }
+        let runs = self.runs.remove(file.path.as_ref()).unwrap_or_default();
+        match file.status.as_deref() {
+            Some(status @ "fail") | Some(status @ "error") => Some(FileResult {
+                path: file.path.to_string(),
+                status: status.to_owned(),
+                total_runs: runs.len(),
+                failed_runs: runs
+                    .into_iter()
+                    .filter(|(_, passed)| !passed)
+                    .map(|(run, _)| run)
+                    .collect(),
+            }),
+            _ => None,
+        }
+    }
+
+    // A line summarising the whole test run, if terraform test got as far as reporting it.
+    pub fn summary(&self, engine: Engine) -> Option<String> {
+        self.totals.as_ref().map(|totals| {
+            format!(
+                "{} test {}: {} passed, {} failed, {} errored, {} skipped",
+                engine.binary(),
+                totals.status,
+                totals.passed,
+                totals.failed,
+                totals.errored,
+                totals.skipped
+            )
+        })
+    }
+}
+
+impl FileResult {
+    // A diagnostic for the whole test file, summarising which runs failed.
+    pub fn to_diagnostic<'a>(
+        &self,
+        path_converter: &dyn Fn(&str) -> Result<String, Error>,
+        source: rd::Source<'a>,
+        engine: Engine,
+    ) -> Result<rd::Diagnostic<'a>, Error> {
+        let message = if self.status == "error" && self.failed_runs.is_empty() {
+            "Test file could not be run".to_owned()
+        } else {
+            format!(
+                "{} of {} test runs failed",
+                self.failed_runs.len(),
+                self.total_runs
+            )
+        };
+        let detail = self
+            .failed_runs
+            .iter()
+            .map(|run| format!("run \"{}\"", run))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(rd::Diagnostic {
+            message: Cow::Owned(message),
+            location: rd::Location {
+                path: path_converter(&self.path)?,
+                range: None,
+            },
+            severity: Some(rd::Severity::Error),
+            source: Some(source),
+            code: Some(rd::Code {
+                value: Cow::Borrowed("test"),
+                url: Some(Cow::Owned(engine.docs_url("test"))),
+            }),
+            suggestions: Vec::new(),
+            original_output: if detail.is_empty() {
+                None
+            } else {
+                Some(Cow::Owned(detail))
+            },
+            snippet: None,
+            summary: None,
+            owners: None,
+            root_module: None,
+        })
+    }
+}
+
+// Notes which test run a diagnostic came from in its detail.
+pub fn annotate(message: &mut tf::StreamMessage) {
+    if let (Some(diag), Some(run)) = (message.diagnostic.as_mut(), &message.test_run_name) {
+        let run_note = format!("(in run \"{}\")", run);
+        diag.detail = Some(Cow::Owned(match diag.detail.as_deref() {
+            Some(detail) if !detail.is_empty() => format!("{}\n\n{}", detail, run_note),
+            _ => run_note,
+        }));
+    }
+}
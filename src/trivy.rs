@@ -0,0 +1,89 @@
+// Trivy misconfiguration scan JSON output definitions
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use crate::scanner::{Finding, SeverityMap};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Report<'a> {
+    // Not used, but required to tell Trivy's output apart from other JSON.
+    #[serde(rename = "SchemaVersion")]
+    _schema_version: u32,
+    #[serde(rename = "ArtifactName")]
+    _artifact_name: Cow<'a, str>,
+    // Left out when there was nothing to scan.
+    #[serde(borrow, default)]
+    pub results: Vec<ScanResult<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ScanResult<'a> {
+    // The scanned file, relative to the directory Trivy scanned.
+    #[serde(borrow)]
+    pub target: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub misconfigurations: Vec<Misconfiguration<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Misconfiguration<'a> {
+    #[serde(rename = "ID", borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub title: Cow<'a, str>,
+    #[serde(borrow, default)]
+    pub message: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    pub resolution: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub severity: Cow<'a, str>,
+    #[serde(rename = "PrimaryURL", borrow, default)]
+    pub primary_url: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    pub references: Vec<Cow<'a, str>>,
+    // PASS results are included when Trivy is run with --include-non-failures.
+    #[serde(borrow)]
+    pub status: Cow<'a, str>,
+    #[serde(default)]
+    pub cause_metadata: CauseMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CauseMetadata {
+    #[serde(default)]
+    pub start_line: u32,
+    #[serde(default)]
+    pub end_line: u32,
+}
+
+impl<'a> Report<'a> {
+    pub fn findings(self, severities: &SeverityMap) -> Vec<Finding<'a>> {
+        let mut findings = Vec::new();
+        for result in self.results {
+            for misconf in result.misconfigurations {
+                if misconf.status != "FAIL" {
+                    continue;
+                }
+                findings.push(Finding {
+                    filename: result.target.clone(),
+                    start_line: misconf.cause_metadata.start_line,
+                    end_line: misconf.cause_metadata.end_line,
+                    severity: severities.get(&misconf.severity),
+                    message: misconf.message.unwrap_or(misconf.title),
+                    rule_id: misconf.id,
+                    url: match misconf.primary_url {
+                        Some(url) => Some(url),
+                        None => misconf.references.into_iter().next(),
+                    },
+                    detail: misconf.resolution,
+                });
+            }
+        }
+        findings
+    }
+}
@@ -0,0 +1,287 @@
+// Watching a directory of Terraform configuration, re-validating the root modules
+// that files change in and reprinting the diagnostics
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use log::{info, warn};
+
+use crate::error::Error;
+use crate::lsp::Validator;
+use crate::modules::{module_files, root_module, run_validate};
+use crate::reviewdog as rd;
+use crate::schema::{self, ProviderSchemas};
+use crate::sources::SourceFiles;
+use crate::{
+    check_format_version, enrich_and_convert, overall_severity, parse_validate_result,
+    path_to_string, validate_codes, write_output, Opt,
+};
+
+// How often to look for changed files.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// The modification time of each Terraform file being watched.
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+// The last validate output of each root module, or the error running it.
+pub type Outputs = BTreeMap<PathBuf, Result<String, Error>>;
+
+pub struct Watcher<'o> {
+    opt: &'o Opt,
+    schema: Option<ProviderSchemas>,
+    validate: Validator,
+    outputs: Outputs,
+}
+
+// Validates every root module under dir, then again each time files in one of
+// them change, until interrupted. The output is printed, or written to a file.
+// A module that can't be validated is reported as an error and watching goes on;
+// only failing to write the output stops it.
+pub fn watch(
+    opt: &Opt,
+    dir: &Path,
+    output: Option<&Path>,
+    debounce: Duration,
+) -> Result<(), Error> {
+    let schema = opt
+        .provider_schema
+        .as_deref()
+        .map(schema::load)
+        .transpose()?;
+    let engine = opt.engine.resolve(None);
+    let mut watcher = Watcher::new(
+        opt,
+        schema,
+        Box::new(move |root| run_validate(engine, root)),
+    );
+    let mut files = snapshot(dir);
+    let mut changed: BTreeSet<PathBuf> = files.keys().cloned().collect();
+    loop {
+        watcher.update(&changed, output)?;
+        let (next, next_changed) = wait_for_changes(dir, &files, debounce);
+        files = next;
+        changed = next_changed;
+    }
+}
+
+impl<'o> Watcher<'o> {
+    pub fn new(opt: &'o Opt, schema: Option<ProviderSchemas>, validate: Validator) -> Self {
+        Watcher {
+            opt,
+            schema,
+            validate,
+            outputs: BTreeMap::new(),
+        }
+    }
+
+    // Validates the root modules of the changed files again and writes out the
+    // diagnostics of all of them.
+    pub fn update(
+        &mut self,
+        changed: &BTreeSet<PathBuf>,
+        output: Option<&Path>,
+    ) -> Result<(), Error> {
+        let roots: BTreeSet<PathBuf> = changed.iter().map(|file| root_module(file)).collect();
+        for root in roots {
+            if root.is_dir() {
+                info!("Validating {}", root.display());
+                let result = (self.validate)(&root);
+                if let Err(e) = &result {
+                    warn!("Can't validate {}: {}", root.display(), e);
+                }
+                self.outputs.insert(root, result);
+            } else {
+                self.outputs.remove(&root);
+            }
+        }
+        report(self.opt, &self.outputs, self.schema.as_ref(), output)
+    }
+}
+
+fn snapshot(dir: &Path) -> Snapshot {
+    module_files(dir).into_iter().collect()
+}
+
+// Waits until files have changed and then stopped changing for the debounce time,
+// as when an editor saves several files or a branch is checked out. Returns the
+// new snapshot and the files that were changed, added or removed.
+fn wait_for_changes(
+    dir: &Path,
+    old: &Snapshot,
+    debounce: Duration,
+) -> (Snapshot, BTreeSet<PathBuf>) {
+    let mut current = loop {
+        thread::sleep(POLL_INTERVAL);
+        let files = snapshot(dir);
+        if &files != old {
+            break files;
+        }
+    };
+    loop {
+        thread::sleep(debounce);
+        let files = snapshot(dir);
+        if files == current {
+            break;
+        }
+        current = files;
+    }
+    let changed = old
+        .keys()
+        .chain(current.keys())
+        .filter(|file| old.get(*file) != current.get(*file))
+        .cloned()
+        .collect();
+    (current, changed)
+}
+
+// The path of a file or directory below the current directory, without a leading
+// "./", or "." for the current directory itself.
+fn relative(path: PathBuf) -> Result<String, Error> {
+    let path = path_to_string(
+        path.strip_prefix(".")
+            .map_or(path.clone(), Path::to_path_buf),
+    )?;
+    Ok(if path.is_empty() {
+        ".".to_owned()
+    } else {
+        path
+    })
+}
+
+// An error diagnostic for a root module that couldn't be validated, so it's shown
+// along with the diagnostics of the others rather than stopping the watch.
+fn failure<'a>(
+    root: &Path,
+    message: String,
+    source: rd::Source<'a>,
+) -> Result<rd::Diagnostic<'a>, Error> {
+    let path = relative(root.to_path_buf())?;
+    Ok(rd::Diagnostic {
+        message: Cow::Owned(message),
+        location: rd::Location {
+            path: path.clone(),
+            range: None,
+        },
+        severity: Some(rd::Severity::Error),
+        source: Some(source),
+        code: None,
+        suggestions: Vec::new(),
+        original_output: None,
+        snippet: None,
+        summary: None,
+        owners: None,
+        root_module: Some(path),
+    })
+}
+
+// Converts the validate output of every root module and writes it out in the
+// format given in the options. Paths are given relative to the current directory.
+pub fn report(
+    opt: &Opt,
+    outputs: &Outputs,
+    schema: Option<&ProviderSchemas>,
+    output: Option<&Path>,
+) -> Result<(), Error> {
+    let mut results = Vec::new();
+    // Root modules that couldn't be validated, with why
+    let mut failures = Vec::new();
+    for (root, text) in outputs {
+        match text {
+            Ok(text) => match parse_validate_result(text, true) {
+                Ok(r) => results.push((root, r)),
+                Err(e) => warn!(
+                    "Can't read the validate output for {}: {}",
+                    root.display(),
+                    e
+                ),
+            },
+            Err(e) => failures.push((root, e.to_string())),
+        }
+    }
+    let engine = opt.engine.for_validate();
+    results.retain(|(root, r)| match check_format_version(r, engine) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(
+                "Can't convert the validate output for {}: {}",
+                root.display(),
+                e
+            );
+            failures.push((root, e.to_string()));
+            false
+        }
+    });
+    let source = opt.source(engine);
+    let overall_sev = overall_severity(
+        results.iter().map(|(_, r)| r.error_count).sum::<u32>() + failures.len() as u32,
+        results.iter().map(|(_, r)| r.warning_count).sum(),
+    );
+    let mut all_diags = Vec::new();
+    for (root, message) in failures {
+        all_diags.push(failure(root, message, source)?);
+    }
+    for (root, r) in &mut results {
+        let sources = SourceFiles::new(Some(root.to_path_buf()));
+        let path_converter = |filename: &str| relative(root.join(filename));
+        let root_module = relative(root.to_path_buf())?;
+        let mut diags = enrich_and_convert(
+            &mut r.diagnostics,
+            &path_converter,
+            &sources,
+            schema,
+            true,
+            source,
+        )?;
+        validate_codes(&mut diags, engine);
+        all_diags.extend(diags.into_iter().map(|mut diag| {
+            diag.root_module = Some(root_module.clone());
+            diag
+        }));
+    }
+    info!(
+        "{} diagnostic(s) in {} root module(s)",
+        all_diags.len(),
+        outputs.len()
+    );
+
+    match output {
+        Some(path) => {
+            // Replace the file in one go, so anything reading it never sees it half written
+            let mut partial = path.as_os_str().to_owned();
+            partial.push(".partial");
+            let mut file = fs::File::create(&partial).map_err(Error::WriteOutput)?;
+            write_output(
+                &mut file,
+                &opt.format,
+                all_diags,
+                overall_sev,
+                source,
+                opt.fail_on,
+                opt.end_position,
+            )?;
+            fs::rename(&partial, path).map_err(Error::WriteOutput)
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            if stdout.is_terminal() {
+                // Clear the screen so only the latest diagnostics are shown
+                write!(out, "\x1b[2J\x1b[H").map_err(Error::WriteOutput)?;
+            }
+            write_output(
+                &mut out,
+                &opt.format,
+                all_diags,
+                overall_sev,
+                source,
+                opt.fail_on,
+                opt.end_position,
+            )?;
+            out.flush().map_err(Error::WriteOutput)
+        }
+    }
+}
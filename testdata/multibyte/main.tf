@@ -0,0 +1,8 @@
+variable "bucket_size" {
+  description = "Größe des Speichers – 🪣"
+  type        = string
+}
+
+locals {
+  name = "ñandú-${var.missing}"
+}
@@ -0,0 +1,15 @@
+variable "names" {
+  type = "list"
+}
+
+variable "ami" {
+  type = string
+}
+
+resource "aws_instance" "web" {
+  ami  = "${var.ami}"
+  tags = "${merge(
+    var.common_tags,
+    { Name = "web" },
+  )}"
+}
@@ -0,0 +1,7 @@
+terraform {
+  source = "git::https://github.com/example/infrastructure-modules.git//app?ref=v1.2.0"
+}
+
+dependency "vpc" {
+  config_path = "../vpc"
+}
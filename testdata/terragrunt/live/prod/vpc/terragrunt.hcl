@@ -0,0 +1,12 @@
+include "root" {
+  path = find_in_parent_folders()
+}
+
+terraform {
+  # Copy all the modules so the vpc module can use the shared ones
+  source = "../../../modules//vpc"
+}
+
+inputs = {
+  cidr_block = "10.0.0.0/16"
+}
@@ -0,0 +1,15 @@
+resource "aws_instance" "web" {
+  ami           = var.ami
+  instance_tpye = "t3.micro"
+  depnds_on     = [aws_vpc.main]
+
+  root_block_device {
+    volume_sise = 20
+  }
+
+  ebs_blok_device {
+    device_name = "/dev/sdf"
+  }
+
+  frobnicate = true
+}